@@ -0,0 +1,56 @@
+//! Benchmarks converting Rust strings into [ParameterString]s.
+//!
+//! These measure the platform's native backend.  To measure the UTF-16 backend on other platforms instead, run
+//! `cargo bench --features utf16-backend`.
+//!
+//! Each conversion is compared against a baseline that reproduces how the UTF-16 backend converted strings before
+//! short strings were stored inline and long strings were measured first: collecting `encode_utf16` into a `Vec`
+//! sized from its size hint, then boxing it.
+//!
+//! To compare against an earlier version, run the benchmark there with `-- --save-baseline before`, then here with
+//! `-- --baseline before`.
+
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pcore::release_pool::autoreleasepool;
+#[cfg(any(target_os = "windows", target_os = "macos", not(feature = "utf16-backend")))]
+use pcore::string::IntoParameterString;
+#[cfg(all(not(any(target_os = "windows", target_os = "macos")), feature = "utf16-backend"))]
+use pcore::string::utf16_backend::IntoParameterString;
+
+fn samples() -> Vec<(&'static str, String)> {
+    vec![
+        ("short ascii", "Documents".to_owned()),
+        ("short mixed", "Café ☕".to_owned()),
+        ("long ascii", "C:\\Users\\Public\\Documents\\".repeat(16)),
+        ("long mixed", "Résumé – 履歴書 – ".repeat(16)),
+    ]
+}
+
+fn conversion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("str");
+    for (name, sample) in samples() {
+        group.throughput(Throughput::Bytes(sample.len() as u64));
+        group.bench_with_input(BenchmarkId::new("into_parameter_string", name), sample.as_str(), |b, s| {
+            autoreleasepool(|pool| {
+                b.iter(|| black_box(black_box(s).into_parameter_string(pool)))
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("baseline", name), sample.as_str(), |b, s| {
+            b.iter(|| {
+                let encode = black_box(s).encode_utf16();
+                let size_hint = encode.size_hint();
+                let mut v = Vec::with_capacity(size_hint.1.unwrap_or(size_hint.0) + 1);
+                for item in encode {
+                    v.push(item);
+                }
+                v.push(0);
+                black_box(v.into_boxed_slice())
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, conversion);
+criterion_main!(benches);
@@ -11,4 +11,8 @@ pub use macos::*;
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use self::windows::*;
\ No newline at end of file
+pub use self::windows::*;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod linux;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use linux::*;
\ No newline at end of file
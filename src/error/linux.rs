@@ -0,0 +1,45 @@
+use std::fmt::{Display, Formatter};
+
+///On Linux, the error type wraps an `errno` value.
+#[derive(Debug)]
+pub struct Error(i32);
+
+impl Error {
+    pub fn from_errno(platform: i32) -> Self {
+        Error(platform)
+    }
+    pub fn into_errno(self) -> i32 {
+        self.0
+    }
+    pub fn as_errno(&self) -> &i32 {
+        &self.0
+    }
+    ///Reads errno.
+    pub fn errno_last() -> Self {
+        Error(std::io::Error::last_os_error().raw_os_error().unwrap_or(0))
+    }
+    ///A string could not be decoded.
+    pub(crate) fn invalid_encoding() -> Self {
+        Error(libc::EILSEQ)
+    }
+    ///A string contained a null before its end.
+    pub(crate) fn interior_nul() -> Self {
+        Error(libc::EINVAL)
+    }
+    ///An allocation failed.
+    pub(crate) fn out_of_memory() -> Self {
+        Error(libc::ENOMEM)
+    }
+    ///A buffer would have to grow past its limit.
+    pub(crate) fn buffer_too_small() -> Self {
+        Error(libc::ERANGE)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&std::io::Error::from_raw_os_error(self.0), f)
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,6 +1,16 @@
-use objr::bindings::StrongCell;
 use std::fmt::{Display, Formatter};
 use objr::foundation::NSError;
+use objr::bindings::*;
+use std::ffi::c_void;
+
+type NSInteger = i64;
+
+objc_selector_group! {
+    trait NSErrorSelectors {
+        @selector("initWithDomain:code:userInfo:")
+    }
+    impl NSErrorSelectors for Sel {}
+}
 
 #[derive(Debug)]
 pub struct Error(StrongCell<NSError>);
@@ -12,6 +22,40 @@ impl Error {
     pub fn into_nserror(self) -> StrongCell<NSError> {
         self.0
     }
+    ///A string could not be decoded.
+    pub(crate) fn invalid_encoding() -> Self {
+        //NSFileReadInapplicableStringEncodingError
+        Error::cocoa_error(261)
+    }
+    ///A string contained a null before its end.
+    pub(crate) fn interior_nul() -> Self {
+        //EINVAL
+        Error::posix_error(22)
+    }
+    ///An allocation failed.
+    pub(crate) fn out_of_memory() -> Self {
+        //ENOMEM
+        Error::posix_error(12)
+    }
+    ///A buffer would have to grow past its limit.
+    pub(crate) fn buffer_too_small() -> Self {
+        //ERANGE
+        Error::posix_error(34)
+    }
+    fn cocoa_error(code: NSInteger) -> Self {
+        autoreleasepool(|pool| unsafe {
+            let alloc = NSError::class().alloc(pool);
+            let raw = NSError::perform(alloc, Sel::initWithDomain_code_userInfo(), pool, (objc_nsstring!("NSCocoaErrorDomain").assume_nonmut_perform(), code, std::ptr::null_mut::<c_void>()));
+            Error(NSError::assume_nonnil(raw).assume_retained())
+        })
+    }
+    fn posix_error(code: NSInteger) -> Self {
+        autoreleasepool(|pool| unsafe {
+            let alloc = NSError::class().alloc(pool);
+            let raw = NSError::perform(alloc, Sel::initWithDomain_code_userInfo(), pool, (objc_nsstring!("NSPOSIXErrorDomain").assume_nonmut_perform(), code, std::ptr::null_mut::<c_void>()));
+            Error(NSError::assume_nonnil(raw).assume_retained())
+        })
+    }
 }
 impl From<StrongCell<NSError>> for Error {
     fn from(e: StrongCell<NSError>) -> Self {
@@ -26,7 +70,7 @@ impl From<Error> for StrongCell<NSError> {
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}",self))
+        f.write_fmt(format_args!("{}",&*self.0))
     }
 }
 
@@ -22,6 +22,29 @@ impl Error {
         use windows::Win32::Foundation::GetLastError;
         Error(unsafe{GetLastError()})
     }
+    ///A string could not be decoded.
+    pub(crate) fn invalid_encoding() -> Self {
+        Error(windows::Win32::Foundation::ERROR_NO_UNICODE_TRANSLATION)
+    }
+    ///A string contained a null before its end.
+    pub(crate) fn interior_nul() -> Self {
+        Error(windows::Win32::Foundation::ERROR_INVALID_PARAMETER)
+    }
+    ///An allocation failed.
+    pub(crate) fn out_of_memory() -> Self {
+        Error(windows::Win32::Foundation::ERROR_NOT_ENOUGH_MEMORY)
+    }
+    ///A buffer would have to grow past its limit.
+    pub(crate) fn buffer_too_small() -> Self {
+        Error(windows::Win32::Foundation::ERROR_INSUFFICIENT_BUFFER)
+    }
+}
+///Errors that don't carry a Win32 error code become `ERROR_INVALID_PARAMETER`.
+impl From<windows::core::Error> for Error {
+    fn from(e: windows::core::Error) -> Self {
+        use windows::Win32::Foundation::ERROR_INVALID_PARAMETER;
+        Error(WIN32_ERROR::from_error(&e).unwrap_or(ERROR_INVALID_PARAMETER))
+    }
 }
 impl From<WIN32_ERROR> for Error {
     fn from(e: WIN32_ERROR) -> Self {
@@ -36,7 +59,7 @@ impl From<Error> for WIN32_ERROR {
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}",self))
+        f.write_fmt(format_args!("Win32 error {}",self.0.0))
     }
 }
 
@@ -15,7 +15,7 @@ pcore is free for noncommercial and "small commercial" use.
 See module [string]
 
 On Windows, pcore uses UTF-16 encoding.  On macOS, pcore uses [objr](https://github.com/drewcrawford/objr) as a
-zero-cost bridge to NSString.  Linux and other platforms are planned.
+zero-cost bridge to NSString.  On Linux, pcore uses null-terminated UTF-8.  Other platforms are planned.
 
 Notably, `pstr!("hello world")` will statically allocate an appropriate string *at compile-time*,
 which is perfect for string constants and similar use cases.
@@ -25,6 +25,9 @@ In particular, upstream [does not support bridging UTF-16 strings](https://githu
 applications on their platform "should use UTF-16 as their internal data representation".  As such,
 this crate is the only way I'm aware of to get zero-cost performance using first-party bindings.
 
+The `utf16-backend` feature also builds the Windows UTF-16 backend on Linux, as `pcore::string::utf16_backend`, so that its
+conversion logic can be tested there.  The native backend is unaffected.
+
 pcore introduces a family of string types for various uses including as API parameters, builders, and more.
 For more information, see the documentation for the `string` module.
 
@@ -10,13 +10,42 @@ which have no effect.  However, using them enables designing APIs with macOS in
 
 Generally, APIs with a likelihood of calling into a platform API should take a [ReleasePool] parameter, this
 enables releasepools to be re-used on macOS across heterogeneous implementations.
+
+A pool may optionally carry a string arena (see [ReleasePool::with_arena]).  On platforms where string conversions
+copy into a new encoding, conversions performed under that pool allocate from the arena instead of the heap.
+
+Long-running loops can use [autoreleasepool_loop], which recycles a single pool every so often, rather than
+creating one pool per iteration or one pool for the whole loop.
+
+Async code can't hold a `&ReleasePool` across `.await`.  Instead, wrap the future with [InReleasePool::in_release_pool]
+and look up the pool for each poll with [with_current_pool].
+
+Threads can be given a long-lived pool with [spawn] or [ThreadBuilderExt].  Thread pools can install one per worker
+with [install_worker_pool]; with the `rayon` feature, `ThreadPoolBuilderExt::with_release_pools` does this for rayon,
+and `ThreadPoolExt` and `ScopeExt` give each rayon task a pool of its own.
+
+To see how much work pools are absorbing, install a hook with [set_statistics_hook].  It receives each pool's [PoolStatistics]
+when the pool is dropped.
 */
 
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "macos")]
 pub use macos::*;
-#[cfg(target_os = "windows")]
+//the Windows implementation has no platform dependencies, so it is used on other platforms as well
+#[cfg(not(target_os = "macos"))]
 mod windows;
-#[cfg(target_os = "windows")]
-pub use self::windows::*;
\ No newline at end of file
+#[cfg(not(target_os = "macos"))]
+pub(crate) mod arena;
+#[cfg(not(target_os = "macos"))]
+pub use self::windows::*;
+pub(crate) mod stack;
+pub use stack::with_current_pool;
+mod statistics;
+pub use statistics::*;
+mod drain;
+pub use drain::*;
+mod future;
+pub use future::*;
+mod thread;
+pub use thread::*;
\ No newline at end of file
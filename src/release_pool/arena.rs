@@ -0,0 +1,131 @@
+/*!
+A bump arena that can be attached to a [ReleasePool](super::ReleasePool).
+
+String conversions performed under a pool with an arena allocate their native-encoded storage from the arena
+instead of boxing a fresh slice per call.  Storage is handed out in chunks; each allocation keeps its chunk alive,
+so a chunk is freed in bulk once the pool and every string allocated from it have been dropped.
+
+The arena has a byte budget.  Once chunk storage would exceed the budget, [Arena::alloc_with] returns `None`
+and callers fall back to the heap.
+*/
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+
+///Size of a newly-allocated chunk, in bytes.  Larger requests get a chunk of their own size.
+const CHUNK_BYTES: usize = 4096;
+
+///A code unit that can be allocated from an [Arena].
+pub(crate) trait Unit: Copy + 'static {}
+impl Unit for u8 {}
+impl Unit for u16 {}
+
+//chunks are allocated as this type, so that they are aligned for every unit
+type ChunkUnit = u16;
+
+///A block of arena storage.
+#[derive(Debug)]
+pub(crate) struct Chunk {
+    ptr: *mut ChunkUnit,
+    len: usize,
+}
+//Regions of a chunk are written once, before they are handed out, and are immutable afterwards.
+unsafe impl Send for Chunk {}
+unsafe impl Sync for Chunk {}
+
+impl Chunk {
+    fn new(bytes: usize) -> Self {
+        let len = bytes / std::mem::size_of::<ChunkUnit>();
+        let boxed = vec![0; len].into_boxed_slice();
+        Chunk { ptr: Box::into_raw(boxed) as *mut ChunkUnit, len }
+    }
+    fn bytes(&self) -> usize {
+        self.len * std::mem::size_of::<ChunkUnit>()
+    }
+}
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(self.ptr, self.len)))
+        }
+    }
+}
+
+///An allocation from an [Arena].  This keeps the underlying chunk alive.
+#[derive(Debug)]
+pub(crate) struct ArenaSlice<U: Unit> {
+    _chunk: Arc<Chunk>,
+    ptr: *const U,
+    len: usize,
+}
+impl<U: Unit> ArenaSlice<U> {
+    pub(crate) fn as_slice(&self) -> &[U] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+//The slice is immutable and the chunk is kept alive by the Arc.
+unsafe impl<U: Unit> Send for ArenaSlice<U> {}
+unsafe impl<U: Unit> Sync for ArenaSlice<U> {}
+
+pub(crate) struct Arena {
+    budget: usize,
+    used: Cell<usize>,
+    //current chunk, and the offset of its first free byte
+    current: RefCell<Option<(Arc<Chunk>, usize)>>,
+}
+
+impl Arena {
+    ///Creates an arena that will allocate at most `budget` bytes of chunk storage.
+    pub(crate) fn new(budget: usize) -> Self {
+        Arena { budget, used: Cell::new(0), current: RefCell::new(None) }
+    }
+    ///The byte budget this arena was created with.
+    pub(crate) fn budget(&self) -> usize {
+        self.budget
+    }
+    ///Bytes of chunk storage allocated so far.
+    pub(crate) fn used(&self) -> usize {
+        self.used.get()
+    }
+    /**
+    Reserves `max_len` units and passes them to `fill`, which returns the number of units it actually wrote.
+
+    Only the written units are consumed; the rest of the reservation is available to the next allocation.
+    Returns `None` if the reservation would exceed the budget, in which case `fill` is not called.
+    */
+    pub(crate) fn alloc_with<U: Unit, F: FnOnce(&mut [U]) -> usize>(&self, max_len: usize, fill: F) -> Option<ArenaSlice<U>> {
+        let max_bytes = max_len * std::mem::size_of::<U>();
+        let align = std::mem::align_of::<U>();
+        let mut current = self.current.borrow_mut();
+        if let Some((_, offset)) = &mut *current {
+            *offset = offset.next_multiple_of(align);
+        }
+        let fits = matches!(&*current, Some((chunk, offset)) if chunk.bytes().saturating_sub(*offset) >= max_bytes);
+        if !fits {
+            let remaining = self.budget - self.used.get();
+            let chunk = Chunk::new(CHUNK_BYTES.max(max_bytes).min(remaining));
+            if chunk.bytes() < max_bytes || chunk.bytes() == 0 {
+                return None;
+            }
+            self.used.set(self.used.get() + chunk.bytes());
+            *current = Some((Arc::new(chunk), 0));
+        }
+        let (chunk, offset) = current.as_mut().unwrap();
+        let start = unsafe { (chunk.ptr as *mut u8).add(*offset) as *mut U };
+        let reserved = unsafe { std::slice::from_raw_parts_mut(start, max_len) };
+        let written = fill(reserved);
+        assert!(written <= max_len);
+        *offset += written * std::mem::size_of::<U>();
+        Some(ArenaSlice { _chunk: chunk.clone(), ptr: start, len: written })
+    }
+}
+
+#[test] fn alloc_within_budget() {
+    let arena = Arena::new(64);
+    let a = arena.alloc_with(4, |buf: &mut [u16]| { buf[..2].copy_from_slice(&[1, 2]); 2 }).unwrap();
+    let b = arena.alloc_with(4, |buf: &mut [u16]| { buf[..3].copy_from_slice(&[3, 4, 5]); 3 }).unwrap();
+    assert_eq!(a.as_slice(), &[1, 2]);
+    assert_eq!(b.as_slice(), &[3, 4, 5]);
+    //both allocations share the first chunk, which was clipped to the budget
+    assert_eq!(arena.used(), 64);
+    assert!(arena.alloc_with(64, |_: &mut [u16]| 0).is_none());
+}
@@ -0,0 +1,106 @@
+use super::ReleasePool;
+use super::stack::Current;
+
+///When to drain the pool used by [autoreleasepool_loop_with].
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum DrainPolicy {
+    ///Drain after every `n` iterations.  `0` behaves like `1`.
+    Iterations(usize),
+    ///Drain once at least `n` objects have been deferred into the pool.  See [ReleasePool::innermost_deferred].
+    Deferred(usize),
+}
+
+/**
+Runs `f` for each item of `iter` under a single pool, draining the pool after every `every_n` iterations.
+
+Creating a pool per iteration is more expensive than necessary, while using one pool for the whole loop grows memory without bound
+on macOS.  This is the compromise.
+
+```
+use pcore::release_pool::autoreleasepool_loop;
+let mut total = 0;
+autoreleasepool_loop(0..100, 10, |_pool, item| {
+    total += item;
+});
+assert_eq!(total, 4950);
+```
+*/
+pub fn autoreleasepool_loop<I: IntoIterator, F: FnMut(&ReleasePool, I::Item)>(iter: I, every_n: usize, f: F) {
+    autoreleasepool_loop_with(iter, DrainPolicy::Iterations(every_n), f)
+}
+
+///Runs `f` for each item of `iter` under a single pool, draining the pool according to `policy`.
+pub fn autoreleasepool_loop_with<I: IntoIterator, F: FnMut(&ReleasePool, I::Item)>(iter: I, policy: DrainPolicy, mut f: F) {
+    let mut pool = unsafe{ ReleasePool::new() };
+    let mut current = unsafe{ Current::enter(&pool) };
+    let mut since_drain = 0;
+    for item in iter {
+        f(&pool, item);
+        since_drain += 1;
+        let should_drain = match policy {
+            DrainPolicy::Iterations(n) => since_drain >= n,
+            DrainPolicy::Deferred(n) => pool.innermost_deferred() >= n,
+        };
+        if should_drain {
+            //the current pool is a shared borrow, so it has to end before the pool is borrowed mutably
+            drop(current);
+            pool.drain();
+            current = unsafe{ Current::enter(&pool) };
+            since_drain = 0;
+        }
+    }
+}
+
+#[test] fn drain_every_n() {
+    let mut seen = Vec::new();
+    autoreleasepool_loop(0..7, 3, |pool, _| {
+        pool.record_deferred(1);
+        seen.push(pool.innermost_deferred());
+    });
+    assert_eq!(seen, [1, 2, 3, 1, 2, 3, 1]);
+}
+
+#[test] fn drain_every_n_deferred() {
+    let mut seen = Vec::new();
+    autoreleasepool_loop_with(0..5, DrainPolicy::Deferred(4), |pool, _| {
+        pool.record_deferred(2);
+        seen.push(pool.innermost_deferred());
+    });
+    assert_eq!(seen, [2, 4, 2, 4, 2]);
+}
+
+#[test] fn drain_resets() {
+    let mut pool = unsafe{ ReleasePool::new() };
+    pool.record_deferred(5);
+    {
+        let inner = unsafe{ ReleasePool::new() };
+        inner.record_deferred(1);
+        assert_eq!(inner.innermost_deferred(), 1);
+    }
+    assert_eq!(pool.innermost_deferred(), 5);
+    pool.drain();
+    assert_eq!(pool.innermost_deferred(), 0);
+}
+
+//under Miri, this checks that draining doesn't invalidate the current pool
+#[test] fn current_across_drain() {
+    use super::with_current_pool;
+    let mut seen = Vec::new();
+    autoreleasepool_loop(0..5, 2, |pool, item| {
+        pool.record_deferred(1);
+        let current = with_current_pool(|current| {
+            assert!(std::ptr::eq(current, pool));
+            current.innermost_deferred()
+        });
+        seen.push((item, current));
+    });
+    assert_eq!(seen, [(0, Some(1)), (1, Some(2)), (2, Some(1)), (3, Some(2)), (4, Some(1))]);
+}
+
+#[test] #[should_panic(expected = "innermost")] fn drain_outer_with_inner_alive() {
+    let mut outer = unsafe{ ReleasePool::new() };
+    super::autoreleasepool(|inner| {
+        inner.record_deferred(1);
+        outer.drain();
+    });
+}
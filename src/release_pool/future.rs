@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use super::autoreleasepool;
+
+/**
+A future that runs every poll of the wrapped future inside a fresh release pool.
+
+A `&ReleasePool` cannot be held across `.await`, so the wrapped future gets at the pool with [with_current_pool](super::with_current_pool),
+which yields a reference valid for the current poll only.
+
+Create one with [InReleasePool::in_release_pool].
+*/
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct PooledFuture<F> {
+    inner: F,
+}
+
+impl<F: Future> Future for PooledFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        //structural pinning: `inner` is never moved out of `self`
+        let inner = unsafe { self.map_unchecked_mut(|s| &mut s.inner) };
+        autoreleasepool(|_pool| inner.poll(cx))
+    }
+}
+
+///Extension methods for running a future inside release pools.
+pub trait InReleasePool: Future + Sized {
+    ///Wraps the future so that each poll runs inside a fresh release pool.  See [PooledFuture].
+    ///
+    /// ```
+    /// use pcore::release_pool::{InReleasePool, with_current_pool};
+    /// let future = async {
+    ///     with_current_pool(|pool| {
+    ///         //call OS APIs that need `pool`
+    ///     }).expect("polled inside a pool");
+    /// }.in_release_pool();
+    /// # drop(future);
+    /// ```
+    fn in_release_pool(self) -> PooledFuture<Self> {
+        PooledFuture { inner: self }
+    }
+}
+impl<F: Future> InReleasePool for F {}
+
+#[cfg(test)] fn block_on<F: Future>(future: F) -> F::Output {
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+    fn raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker { raw_waker() }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test] fn pool_per_poll() {
+    use super::with_current_pool;
+    struct YieldOnce(bool);
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            }
+            else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+    let deferred = block_on(async {
+        let first = with_current_pool(|pool| { pool.record_deferred(3); pool.innermost_deferred() });
+        YieldOnce(false).await;
+        //the second poll runs in a new pool
+        let second = with_current_pool(|pool| pool.innermost_deferred());
+        (first, second)
+    }.in_release_pool());
+    assert_eq!(deferred, (Some(3), Some(0)));
+    assert!(with_current_pool(|_| ()).is_none());
+}
@@ -1,13 +1,34 @@
 use objr::bindings::{AutoreleasePool, ActiveAutoreleasePool};
 use std::ops::Deref;
+use super::stack::{self, Current, Registration};
 
-///This type can be deferenced to get a platform-specific pool type.
-#[repr(transparent)]
-pub struct ReleasePool(AutoreleasePool);
+/**
+This type can be deferenced to get a platform-specific pool type.
+
+On macOS, this is objr's `ActiveAutoreleasePool`.  The reverse isn't possible: a `ReleasePool` also tracks pcore's
+per-thread bookkeeping, which an `ActiveAutoreleasePool` doesn't have.
+
+Pools belong to the thread that created them, so they can't be sent to another thread.
+
+```compile_fail
+use pcore::release_pool::ReleasePool;
+let pool = unsafe{ ReleasePool::new() };
+std::thread::spawn(move || drop(pool));
+```
+*/
+pub struct ReleasePool(AutoreleasePool, Registration);
 
 ///Creates an autoreleasepool.
 pub fn autoreleasepool<F: FnOnce(&ReleasePool) -> R,R>(f: F) -> R {
     let a = unsafe{ ReleasePool::new() };
+    let _current = unsafe{ Current::enter(&a) };
+    f(&a)
+}
+
+///Creates an autoreleasepool with a string arena of `budget` bytes.  See [ReleasePool::with_arena].
+pub fn autoreleasepool_with_arena<F: FnOnce(&ReleasePool) -> R,R>(budget: usize, f: F) -> R {
+    let a = unsafe{ ReleasePool::with_arena(budget) };
+    let _current = unsafe{ Current::enter(&a) };
     f(&a)
 }
 
@@ -18,7 +39,64 @@ impl ReleasePool {
     /// Autorelease pools must be dropped in reverse order to when they are created. If you don't want to maintain
     /// this invariant yourself, see the [autoreleasepool] safe wrapper.
     pub unsafe fn new() -> Self {
-        ReleasePool(AutoreleasePool::new())
+        ReleasePool(AutoreleasePool::new(), Registration::push())
+    }
+    ///Creates a new pool with a string arena.
+    ///
+    /// On macOS, string conversions bridge into `NSString` without copying into pcore-managed storage, so no arena is attached
+    /// and `budget` is ignored.  This API exists so that cross-platform code can request an arena.
+    ///
+    /// # Safety
+    /// Same as [ReleasePool::new].  If you don't want to maintain this invariant yourself, see the [autoreleasepool_with_arena] safe wrapper.
+    pub unsafe fn with_arena(_budget: usize) -> Self {
+        Self::new()
+    }
+    ///Drains the pool, releasing everything deferred into it so far.  The pool remains usable afterwards.
+    ///
+    /// On macOS, this pops the underlying autorelease pool and pushes a new one in its place.  Taking `&mut self` guarantees
+    /// no autoreleased references into the pool are still borrowed.
+    ///
+    /// # Panics
+    /// If a nested pool is still alive on this thread, since autorelease pools can only be popped innermost first.
+    pub fn drain(&mut self) {
+        assert!(self.1.is_innermost(), "Only the innermost release pool on a thread can be drained");
+        unsafe {
+            //the old pool must be popped before the new one is pushed
+            std::ptr::drop_in_place(&mut self.0);
+            std::ptr::write(&mut self.0, AutoreleasePool::new());
+        }
+        self.1.reset_deferred();
+    }
+    ///Records that `count` objects were deferred into the innermost pool on this thread.
+    ///
+    /// Bindings that autorelease objects should call this, so that
+    /// [DrainPolicy::Deferred](super::DrainPolicy::Deferred) can account for them.
+    pub fn record_deferred(&self, count: usize) {
+        stack::record_deferred(count)
+    }
+    ///Records that `bytes` were deferred into the innermost pool on this thread.  This is reported in [PoolStatistics](super::PoolStatistics).
+    pub fn record_deferred_bytes(&self, bytes: usize) {
+        stack::record_deferred_bytes(bytes)
+    }
+    ///Statistics so far for the innermost pool on this thread.  See [PoolStatistics](super::PoolStatistics).
+    ///
+    /// This is this pool, unless a nested pool is active, in which case it's the nested pool.
+    pub fn innermost_statistics(&self) -> super::PoolStatistics {
+        stack::statistics()
+    }
+    ///The number of objects deferred into the innermost pool on this thread since it was created or last drained.
+    ///
+    /// This is this pool, unless a nested pool is active, in which case it's the nested pool.
+    pub fn innermost_deferred(&self) -> usize {
+        stack::deferred()
+    }
+    ///The arena's byte budget, if this pool has an arena.  Always `None` on macOS.
+    pub fn arena_budget(&self) -> Option<usize> {
+        None
+    }
+    ///Bytes the arena has allocated so far, if this pool has an arena.  Always `None` on macOS.
+    pub fn arena_used(&self) -> Option<usize> {
+        None
     }
 }
 
@@ -29,11 +107,3 @@ impl Deref for ReleasePool {
         &self.0
     }
 }
-impl From<&ActiveAutoreleasePool> for &ReleasePool {
-    fn from(f: &ActiveAutoreleasePool) -> Self {
-        //we are layout-compatible with AutoreleasePool.
-        unsafe {
-            std::mem::transmute(f)
-        }
-    }
-}
@@ -0,0 +1,191 @@
+/*!
+Per-thread bookkeeping for active pools.
+
+Pools on a thread are strictly nested (see [ReleasePool::new](super::ReleasePool::new)), so they form a stack.
+Like autoreleased objects on macOS, deferred objects are attributed to the innermost pool on the current thread.
+*/
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::time::Instant;
+use super::ReleasePool;
+use super::statistics::{self, PoolStatistics};
+
+struct Entry {
+    //objects deferred since the pool was created or last drained
+    deferred: usize,
+    statistics: PoolStatistics,
+    created: Option<Instant>,
+    //values kept alive until the pool is drained or dropped
+    storage: Vec<Box<dyn Any>>,
+}
+
+thread_local! {
+    static STACK: RefCell<Vec<Entry>> = const { RefCell::new(Vec::new()) };
+    //conversions not yet added to the innermost entry, which is cheaper than borrowing the stack for each one
+    static CONVERSIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+///Registers a pool on the current thread's stack for as long as this value is alive.
+///
+/// This isn't `Send`, so that the pool is dropped on the thread whose stack it was pushed onto.
+pub(crate) struct Registration {
+    //index of this pool's entry in the stack
+    depth: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Registration {
+    pub(crate) fn push() -> Self {
+        let created = if statistics::enabled() { Some(Instant::now()) } else { None };
+        let depth = STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            flush_conversions(&mut stack);
+            let statistics = PoolStatistics { peak_depth: stack.len() + 1, ..PoolStatistics::default() };
+            stack.push(Entry { deferred: 0, statistics, created, storage: Vec::new() });
+            stack.len() - 1
+        });
+        Registration { depth, _not_send: PhantomData }
+    }
+    ///Whether this is the innermost pool on the current thread.
+    pub(crate) fn is_innermost(&self) -> bool {
+        STACK.with(|stack| stack.borrow().len() == self.depth + 1)
+    }
+    ///Resets this pool's count and releases its deferred storage, because it was drained.
+    pub(crate) fn reset_deferred(&self) {
+        let storage = STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let entry = &mut stack[self.depth];
+            entry.deferred = 0;
+            entry.statistics.drains += 1;
+            std::mem::take(&mut entry.storage)
+        });
+        //outside the borrow of the stack
+        drop(storage);
+    }
+}
+impl Drop for Registration {
+    fn drop(&mut self) {
+        //during thread teardown the stack may already be gone
+        let popped = STACK.try_with(|stack| {
+            let mut stack = stack.borrow_mut();
+            flush_conversions(&mut stack);
+            let mut entry = stack.pop()?;
+            if let Some(parent) = stack.last_mut() {
+                parent.statistics.peak_depth = parent.statistics.peak_depth.max(entry.statistics.peak_depth);
+            }
+            if let Some(created) = entry.created {
+                entry.statistics.lifetime = created.elapsed();
+            }
+            Some(entry.statistics)
+        });
+        if let Ok(Some(popped)) = popped {
+            statistics::report(&popped);
+        }
+    }
+}
+
+fn with_top<R, F: FnOnce(&mut Entry) -> R>(f: F) -> Option<R> {
+    STACK.with(|stack| stack.borrow_mut().last_mut().map(f))
+}
+///Adds pending conversions to the innermost entry, before it stops being innermost or its statistics are read.
+fn flush_conversions(stack: &mut [Entry]) {
+    //conversions with no pool registered are discarded
+    let conversions = CONVERSIONS.with(|c| c.replace(0));
+    if let Some(top) = stack.last_mut() {
+        top.statistics.conversions += conversions;
+    }
+}
+
+///Records `count` objects deferred into the innermost pool.  Has no effect if no pool is registered.
+pub(crate) fn record_deferred(count: usize) {
+    with_top(|top| {
+        top.deferred += count;
+        top.statistics.objects_deferred += count;
+    });
+}
+///Records `bytes` deferred into the innermost pool.  Has no effect if no pool is registered.
+pub(crate) fn record_deferred_bytes(bytes: usize) {
+    with_top(|top| top.statistics.bytes_deferred += bytes);
+}
+///Records a string conversion performed under the innermost pool.  Has no effect if no pool is registered.
+pub(crate) fn record_conversion() {
+    CONVERSIONS.with(|c| c.set(c.get() + 1));
+}
+///Objects deferred into the innermost pool since it was created or last drained.
+pub(crate) fn deferred() -> usize {
+    with_top(|top| top.deferred).unwrap_or(0)
+}
+///Keeps `storage` alive until the innermost pool is drained or dropped, and records it as deferred.
+///
+/// # Panics
+/// If no pool is registered on this thread.
+#[cfg_attr(not(any(target_os = "windows", all(unix, not(target_os = "macos")))), allow(dead_code))]
+pub(crate) fn defer_drop<T: 'static>(storage: T) {
+    with_top(|top| {
+        top.storage.push(Box::new(storage));
+        top.deferred += 1;
+        top.statistics.objects_deferred += 1;
+    }).expect("No release pool is active on this thread");
+}
+///Statistics for the innermost pool so far.
+pub(crate) fn statistics() -> PoolStatistics {
+    STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        flush_conversions(&mut stack);
+        stack.last().map(|top| {
+            let mut statistics = top.statistics.clone();
+            if let Some(created) = top.created {
+                statistics.lifetime = created.elapsed();
+            }
+            statistics
+        }).unwrap_or_default()
+    })
+}
+
+thread_local! {
+    static CURRENT: Cell<*const ReleasePool> = const { Cell::new(std::ptr::null()) };
+}
+
+///Makes a pool the current pool on this thread for as long as this value is alive.  See [with_current_pool].
+pub(crate) struct Current(*const ReleasePool);
+
+impl Current {
+    ///# Safety
+    /// `pool` must outlive the returned value, and must not be moved while it is alive.
+    pub(crate) unsafe fn enter(pool: *const ReleasePool) -> Self {
+        Current(CURRENT.with(|current| current.replace(pool)))
+    }
+}
+impl Drop for Current {
+    fn drop(&mut self) {
+        let _ = CURRENT.try_with(|current| current.set(self.0));
+    }
+}
+
+/**
+Calls `f` with the current pool on this thread, if there is one.
+
+A pool is current inside [autoreleasepool](super::autoreleasepool) and the other safe wrappers in this module, and for the duration
+of each poll of a [PooledFuture](super::PooledFuture).  The reference cannot escape `f`, so it is valid even for code (such as a future)
+that cannot hold a `&ReleasePool` itself.
+
+Returns `None` if no pool is current.
+
+```
+use pcore::release_pool::{autoreleasepool, with_current_pool};
+assert!(with_current_pool(|_pool| ()).is_none());
+autoreleasepool(|_pool| {
+    assert!(with_current_pool(|_pool| ()).is_some());
+});
+```
+*/
+pub fn with_current_pool<R, F: FnOnce(&ReleasePool) -> R>(f: F) -> Option<R> {
+    let pool = CURRENT.with(|current| current.get());
+    if pool.is_null() {
+        None
+    }
+    else {
+        Some(f(unsafe { &*pool }))
+    }
+}
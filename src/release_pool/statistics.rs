@@ -0,0 +1,119 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/**
+Statistics about the work absorbed by a single pool.
+
+Counters are attributed to the innermost pool on the current thread, the same way autoreleased objects are on macOS.
+
+Statistics are reported to the hook installed with [set_statistics_hook] when a pool is dropped.
+They can also be read from a live pool with [ReleasePool::innermost_statistics](super::ReleasePool::innermost_statistics).
+*/
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+#[non_exhaustive]
+pub struct PoolStatistics {
+    ///Objects deferred into the pool, over its whole lifetime.  See [ReleasePool::record_deferred](super::ReleasePool::record_deferred).
+    pub objects_deferred: usize,
+    ///Bytes deferred into the pool, over its whole lifetime.  See [ReleasePool::record_deferred_bytes](super::ReleasePool::record_deferred_bytes).
+    pub bytes_deferred: usize,
+    ///The deepest nesting of pools on this thread while the pool was alive.  An un-nested pool has depth 1.
+    pub peak_depth: usize,
+    ///How long the pool has been alive.
+    ///
+    /// This is only measured if a hook was installed when the pool was created; otherwise it is zero.
+    pub lifetime: Duration,
+    ///String conversions performed under the pool.
+    pub conversions: usize,
+    ///How many times the pool was drained.
+    pub drains: usize,
+}
+
+type Hook = Arc<dyn Fn(&PoolStatistics) + Send + Sync>;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static HOOK: Mutex<Option<Hook>> = Mutex::new(None);
+
+/**
+Installs a process-wide hook that is called with each pool's statistics when the pool is dropped.
+
+This replaces any previous hook.  Statistics are opt-in: pool lifetimes are only measured while a hook is installed.
+
+```
+use pcore::release_pool::{autoreleasepool, clear_statistics_hook, set_statistics_hook};
+set_statistics_hook(|statistics| {
+    println!("pool statistics {:?}", statistics);
+});
+autoreleasepool(|_pool| {
+    //...
+});
+clear_statistics_hook();
+```
+*/
+pub fn set_statistics_hook<F: Fn(&PoolStatistics) + Send + Sync + 'static>(hook: F) {
+    let mut guard = HOOK.lock().unwrap();
+    *guard = Some(Arc::new(hook));
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+///Removes the hook installed with [set_statistics_hook].
+pub fn clear_statistics_hook() {
+    let mut guard = HOOK.lock().unwrap();
+    *guard = None;
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn report(statistics: &PoolStatistics) {
+    if !enabled() {
+        return;
+    }
+    //don't hold the lock while calling the hook, which may itself use pools
+    let hook = HOOK.lock().unwrap().clone();
+    if let Some(hook) = hook {
+        hook(statistics)
+    }
+}
+
+#[test] fn statistics_reported_on_drop() {
+    use super::autoreleasepool;
+    static REPORTED: Mutex<Vec<PoolStatistics>> = Mutex::new(Vec::new());
+    //the hook is process-wide, so ignore pools from tests on other threads
+    let test_thread = std::thread::current().id();
+    set_statistics_hook(move |statistics| {
+        if std::thread::current().id() == test_thread {
+            REPORTED.lock().unwrap().push(statistics.clone());
+        }
+    });
+    autoreleasepool(|pool| {
+        pool.record_deferred(2);
+        pool.record_deferred_bytes(64);
+        autoreleasepool(|inner| {
+            autoreleasepool(|_| {});
+            inner.record_deferred(1);
+            assert_eq!(inner.innermost_statistics().peak_depth, 3);
+        });
+    });
+    clear_statistics_hook();
+    let reported = REPORTED.lock().unwrap();
+    let summary: Vec<_> = reported.iter().map(|s| (s.objects_deferred, s.bytes_deferred, s.peak_depth)).collect();
+    assert_eq!(summary, [(0, 0, 3), (1, 0, 3), (2, 64, 3)]);
+}
+
+#[test] fn conversions_attributed_to_innermost() {
+    use super::autoreleasepool;
+    use super::stack::record_conversion;
+    autoreleasepool(|outer| {
+        record_conversion();
+        autoreleasepool(|inner| {
+            record_conversion();
+            record_conversion();
+            assert_eq!(inner.innermost_statistics().conversions, 2);
+        });
+        record_conversion();
+        assert_eq!(outer.innermost_statistics().conversions, 2);
+    });
+}
@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::io;
+use std::thread::JoinHandle;
+use super::{autoreleasepool, ReleasePool};
+use super::stack::Current;
+
+/**
+Spawns a thread that runs `f` inside a release pool.
+
+The pool lives for the whole thread, and is the current pool (see [with_current_pool](super::with_current_pool)) while `f` runs.
+Work items within the thread can nest a shorter-lived pool with [autoreleasepool].
+
+```
+use pcore::release_pool::spawn;
+let handle = spawn(|_pool| 2 + 2);
+assert_eq!(handle.join().unwrap(), 4);
+```
+*/
+pub fn spawn<F: FnOnce(&ReleasePool) -> T + Send + 'static, T: Send + 'static>(f: F) -> JoinHandle<T> {
+    std::thread::spawn(move || autoreleasepool(f))
+}
+
+///Extension methods for [std::thread::Builder].
+pub trait ThreadBuilderExt {
+    ///Spawns a thread that runs `f` inside a release pool.  See [spawn].
+    fn spawn_in_release_pool<F: FnOnce(&ReleasePool) -> T + Send + 'static, T: Send + 'static>(self, f: F) -> io::Result<JoinHandle<T>>;
+}
+impl ThreadBuilderExt for std::thread::Builder {
+    fn spawn_in_release_pool<F: FnOnce(&ReleasePool) -> T + Send + 'static, T: Send + 'static>(self, f: F) -> io::Result<JoinHandle<T>> {
+        self.spawn(move || autoreleasepool(f))
+    }
+}
+
+//fields drop in order, so the pool stops being current before it is dropped
+struct WorkerPool {
+    _current: Current,
+    _pool: Box<ReleasePool>,
+}
+
+thread_local! {
+    static WORKER_POOL: RefCell<Option<WorkerPool>> = const { RefCell::new(None) };
+}
+
+/**
+Installs a long-lived pool on the current thread, for use in a thread pool's worker start hook.
+
+The pool is the current pool (see [with_current_pool](super::with_current_pool)) until [uninstall_worker_pool] is called.
+
+# Safety
+Pools must be dropped in reverse order to when they are created.  So this must be called before any other pool is created on this thread,
+and [uninstall_worker_pool] must be called after every other pool on this thread has been dropped.  Generally this means calling
+these functions at the very start and end of the worker thread.
+
+# Panics
+If a worker pool is already installed on this thread.
+*/
+pub unsafe fn install_worker_pool() {
+    let pool = Box::new(ReleasePool::new());
+    let current = Current::enter(&*pool);
+    WORKER_POOL.with(|worker| {
+        let mut worker = worker.borrow_mut();
+        assert!(worker.is_none(), "A worker pool is already installed on this thread");
+        *worker = Some(WorkerPool { _current: current, _pool: pool });
+    })
+}
+
+/**
+Drops the pool installed by [install_worker_pool], for use in a thread pool's worker exit hook.
+
+Has no effect if no worker pool is installed on this thread.
+
+# Safety
+See [install_worker_pool].
+*/
+pub unsafe fn uninstall_worker_pool() {
+    let worker = WORKER_POOL.with(|worker| worker.borrow_mut().take());
+    drop(worker)
+}
+
+///Extension methods for [rayon::ThreadPoolBuilder].
+#[cfg(feature = "rayon")]
+pub trait ThreadPoolBuilderExt {
+    /**
+    Installs a long-lived release pool on each worker thread, using the builder's start and exit handlers.
+
+    **This replaces any start or exit handler set previously.**  To keep your own handlers, pass them to
+    [ThreadPoolBuilderExt::with_release_pools_and_handlers] instead.
+
+    Tasks that need a shorter-lived pool can use [ThreadPoolExt] or [ScopeExt], or nest one with [autoreleasepool].
+    */
+    fn with_release_pools(self) -> Self;
+    /**
+    Installs a long-lived release pool on each worker thread, and runs `start` and `exit` as the builder's start and exit handlers.
+
+    `start` runs after the worker's pool is installed, and `exit` runs before it is dropped, so both can use the pool.
+    Like [ThreadPoolBuilderExt::with_release_pools], this replaces any start or exit handler set previously.
+    */
+    fn with_release_pools_and_handlers<S: Fn(usize) + Send + Sync + 'static, E: Fn(usize) + Send + Sync + 'static>(self, start: S, exit: E) -> Self;
+}
+#[cfg(feature = "rayon")]
+impl<S> ThreadPoolBuilderExt for rayon::ThreadPoolBuilder<S> {
+    fn with_release_pools(self) -> Self {
+        self.with_release_pools_and_handlers(|_| (), |_| ())
+    }
+    fn with_release_pools_and_handlers<St: Fn(usize) + Send + Sync + 'static, E: Fn(usize) + Send + Sync + 'static>(self, start: St, exit: E) -> Self {
+        //worker threads start with no pools, and each worker's tasks finish before the exit handler runs
+        self.start_handler(move |index| {
+            unsafe { install_worker_pool() };
+            start(index)
+        })
+        .exit_handler(move |index| {
+            exit(index);
+            unsafe { uninstall_worker_pool() }
+        })
+    }
+}
+
+///Extension methods for [rayon::ThreadPool], to run each task inside its own release pool.
+#[cfg(feature = "rayon")]
+pub trait ThreadPoolExt {
+    ///Like [rayon::ThreadPool::install], but runs `f` inside a release pool.
+    fn install_in_release_pool<F: FnOnce(&ReleasePool) -> R + Send, R: Send>(&self, f: F) -> R;
+    ///Like [rayon::ThreadPool::spawn], but runs `f` inside a release pool.
+    fn spawn_in_release_pool<F: FnOnce(&ReleasePool) + Send + 'static>(&self, f: F);
+}
+#[cfg(feature = "rayon")]
+impl ThreadPoolExt for rayon::ThreadPool {
+    fn install_in_release_pool<F: FnOnce(&ReleasePool) -> R + Send, R: Send>(&self, f: F) -> R {
+        self.install(|| autoreleasepool(f))
+    }
+    fn spawn_in_release_pool<F: FnOnce(&ReleasePool) + Send + 'static>(&self, f: F) {
+        self.spawn(|| autoreleasepool(f))
+    }
+}
+
+///Extension methods for [rayon::Scope], to run each task inside its own release pool.
+#[cfg(feature = "rayon")]
+pub trait ScopeExt<'scope> {
+    ///Like [rayon::Scope::spawn], but runs `f` inside a release pool.
+    fn spawn_in_release_pool<F: FnOnce(&ReleasePool, &rayon::Scope<'scope>) + Send + 'scope>(&self, f: F);
+}
+#[cfg(feature = "rayon")]
+impl<'scope> ScopeExt<'scope> for rayon::Scope<'scope> {
+    fn spawn_in_release_pool<F: FnOnce(&ReleasePool, &rayon::Scope<'scope>) + Send + 'scope>(&self, f: F) {
+        self.spawn(|scope| autoreleasepool(|pool| f(pool, scope)))
+    }
+}
+
+#[test] fn spawn_current() {
+    use super::with_current_pool;
+    let handle = spawn(|_pool| with_current_pool(|_| ()).is_some());
+    assert!(handle.join().unwrap());
+    let handle = std::thread::Builder::new().spawn_in_release_pool(|_pool| with_current_pool(|_| ()).is_some()).unwrap();
+    assert!(handle.join().unwrap());
+}
+
+#[test] fn worker_pool() {
+    use super::with_current_pool;
+    let handle = std::thread::spawn(|| {
+        unsafe { install_worker_pool() };
+        let installed = with_current_pool(|_| ()).is_some();
+        unsafe { uninstall_worker_pool() };
+        (installed, with_current_pool(|_| ()).is_some())
+    });
+    assert_eq!(handle.join().unwrap(), (true, false));
+}
+
+#[cfg(feature = "rayon")]
+#[test] fn rayon_worker_pool() {
+    use super::with_current_pool;
+    let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(2).with_release_pools().build().unwrap();
+    assert!(thread_pool.install(|| with_current_pool(|_| ()).is_some()));
+}
+
+#[cfg(feature = "rayon")]
+#[test] fn rayon_chained_handlers() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use super::with_current_pool;
+    let started = Arc::new(AtomicUsize::new(0));
+    let exited = Arc::new(AtomicUsize::new(0));
+    let (start_count, exit_count) = (started.clone(), exited.clone());
+    let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(2).with_release_pools_and_handlers(
+        move |_| if with_current_pool(|_| ()).is_some() { start_count.fetch_add(1, Ordering::SeqCst); },
+        move |_| if with_current_pool(|_| ()).is_some() { exit_count.fetch_add(1, Ordering::SeqCst); },
+    ).build().unwrap();
+    thread_pool.install(|| ());
+    drop(thread_pool);
+    //the pool shuts down asynchronously
+    while exited.load(Ordering::SeqCst) < 2 {
+        std::thread::yield_now();
+    }
+    assert_eq!(started.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "rayon")]
+#[test] fn rayon_task_pools() {
+    let thread_pool = rayon::ThreadPoolBuilder::new().num_threads(2).with_release_pools().build().unwrap();
+    //each task's pool is nested inside the worker's pool
+    let depth = thread_pool.install_in_release_pool(|pool| pool.innermost_statistics().peak_depth);
+    assert_eq!(depth, 2);
+    let depths = std::sync::Mutex::new(Vec::new());
+    thread_pool.scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn_in_release_pool(|pool, _| depths.lock().unwrap().push(pool.innermost_statistics().peak_depth));
+        }
+    });
+    assert_eq!(depths.into_inner().unwrap(), [2; 4]);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    thread_pool.spawn_in_release_pool(move |pool| sender.send(pool.innermost_statistics().peak_depth).unwrap());
+    assert_eq!(receiver.recv().unwrap(), 2);
+}
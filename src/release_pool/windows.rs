@@ -1,11 +1,34 @@
 use std::ops::Deref;
+use super::arena::Arena;
+use super::stack::{self, Current, Registration};
 
-///This type can be deferenced to get a platform-specific pool type.
-pub struct ReleasePool;
+/**
+This type can be deferenced to get a platform-specific pool type.
+
+Pools belong to the thread that created them, so they can't be sent to another thread.
+
+```compile_fail
+use pcore::release_pool::ReleasePool;
+let pool = unsafe{ ReleasePool::new() };
+std::thread::spawn(move || drop(pool));
+```
+*/
+pub struct ReleasePool {
+    arena: Option<Arena>,
+    _registration: Option<Registration>,
+}
 
 ///Creates an autoreleasepool.
 pub fn autoreleasepool<F: FnOnce(&ReleasePool) -> R,R>(f: F) -> R {
     let a = unsafe{ ReleasePool::new() };
+    let _current = unsafe{ Current::enter(&a) };
+    f(&a)
+}
+
+///Creates an autoreleasepool with a string arena of `budget` bytes.  See [ReleasePool::with_arena].
+pub fn autoreleasepool_with_arena<F: FnOnce(&ReleasePool) -> R,R>(budget: usize, f: F) -> R {
+    let a = unsafe{ ReleasePool::with_arena(budget) };
+    let _current = unsafe{ Current::enter(&a) };
     f(&a)
 }
 
@@ -18,13 +41,75 @@ impl ReleasePool {
     ///
     /// On Windows, this API has no effect.
     pub unsafe fn new() -> Self {
-        ReleasePool
+        ReleasePool { arena: None, _registration: Some(Registration::push()) }
+    }
+    ///Creates a new pool with a string arena.
+    ///
+    /// String conversions performed under this pool (for example, `&str` into a `ParameterString`) allocate from the arena
+    /// rather than the heap.  Arena storage is freed in bulk once the pool, and every string allocated from it, are dropped.
+    /// Once the arena has allocated `budget` bytes, further conversions fall back to the heap.
+    ///
+    /// # Safety
+    /// Same as [ReleasePool::new].  If you don't want to maintain this invariant yourself, see the [autoreleasepool_with_arena] safe wrapper.
+    pub unsafe fn with_arena(budget: usize) -> Self {
+        ReleasePool { arena: Some(Arena::new(budget)), _registration: Some(Registration::push()) }
     }
     ///Assumes that a release pool is active
     ///
     /// # Safety
     /// Not safe is no release pool is active
-    pub unsafe fn assuming_pool() -> &'static ReleasePool { &ReleasePool }
+    pub unsafe fn assuming_pool() -> &'static ReleasePool { &ReleasePool { arena: None, _registration: None } }
+
+    ///Drains the pool, releasing everything deferred into it so far.  The pool remains usable afterwards.
+    ///
+    /// On Windows, this starts a fresh arena (if the pool has one) with the same budget.  Strings already allocated
+    /// from the old arena remain valid; its storage is freed once they are dropped.
+    ///
+    /// # Panics
+    /// If a nested pool is still alive on this thread, since values deferred into it may still be in use.
+    pub fn drain(&mut self) {
+        if let Some(registration) = &self._registration {
+            assert!(registration.is_innermost(), "Only the innermost release pool on a thread can be drained");
+            registration.reset_deferred();
+        }
+        if let Some(arena) = &self.arena {
+            self.arena = Some(Arena::new(arena.budget()));
+        }
+    }
+    ///Records that `count` objects were deferred into the innermost pool on this thread.
+    ///
+    /// Bindings that defer releasing objects until the pool is drained should call this, so that
+    /// [DrainPolicy::Deferred](super::DrainPolicy::Deferred) can account for them.
+    pub fn record_deferred(&self, count: usize) {
+        stack::record_deferred(count)
+    }
+    ///Records that `bytes` were deferred into the innermost pool on this thread.  This is reported in [PoolStatistics](super::PoolStatistics).
+    pub fn record_deferred_bytes(&self, bytes: usize) {
+        stack::record_deferred_bytes(bytes)
+    }
+    ///Statistics so far for the innermost pool on this thread.  See [PoolStatistics](super::PoolStatistics).
+    ///
+    /// This is this pool, unless a nested pool is active, in which case it's the nested pool.
+    pub fn innermost_statistics(&self) -> super::PoolStatistics {
+        stack::statistics()
+    }
+    ///The number of objects deferred into the innermost pool on this thread since it was created or last drained.
+    ///
+    /// This is this pool, unless a nested pool is active, in which case it's the nested pool.
+    pub fn innermost_deferred(&self) -> usize {
+        stack::deferred()
+    }
+    pub(crate) fn arena(&self) -> Option<&Arena> {
+        self.arena.as_ref()
+    }
+    ///The arena's byte budget, if this pool has an arena.
+    pub fn arena_budget(&self) -> Option<usize> {
+        self.arena.as_ref().map(|a| a.budget())
+    }
+    ///Bytes the arena has allocated so far, if this pool has an arena.
+    pub fn arena_used(&self) -> Option<usize> {
+        self.arena.as_ref().map(|a| a.used())
+    }
 }
 
 impl Deref for ReleasePool {
@@ -15,12 +15,39 @@ What we want is:
 
 To solve this, `pcore` implements a variety of 'API' string types.
 
-* [IntoParameterString] is a trait you'd use for a function parameter.
+* [IntoParameterString] is a trait you'd use for a function parameter.  Its `try_into_parameter_string` method reports
+  strings that can't be converted (for example, because they contain an interior null) as an [Error](crate::error::Error),
+  where `into_parameter_string` would panic.
 * [ParameterString] erases an [IntoParameterString] into a concrete type.  This is appropriate for short-term use where the lifetime is tracked, such as a builder pattern.
 * [OwnedString] copies the storage from an [IntoParameterString] and has `'static` lifetime.  This might be appropriate for long-term use in a struct field.
+* [SharedString] is a reference-counted string that is cheap to clone and can be shared between threads.
+* [MaybeOwnedString] is either borrowed or an [OwnedString], like [Cow](std::borrow::Cow), and only copies when an owned string is needed.
+* [Atom] interns a string once per process, for constant-time comparison and hashing.
+* [ArrayString] is a fixed-capacity string stored inline, for building short strings without the heap.
+* [FromPlatformString] is the reverse of [IntoParameterString], converting platform strings into types like [String] or [std::path::PathBuf].
+* [OwnedStringBuilder] assembles an [OwnedString] a piece at a time, in the platform's encoding.
+* [StringBuffer] calls APIs that fill a caller-provided buffer, growing it until the string fits, and yields an [OwnedString].
 * [pstr!] is a macro that gets strings into the correct format at compile-time to avoid runtime encoding.  The return type conforms to [IntoParameterString].
+* [pformat!](crate::pformat) is like [format!], but formats straight into an [OwnedString] in the platform's encoding.
+
+[ParameterString], [OwnedString] and [PStr] all dereference to [NativeStr], a borrowed string in the platform's encoding,
+which can be read back into Rust strings on every platform, with methods like
+`to_string_lossy`, `try_to_string`, `len_utf8`, `len_utf16`, `is_empty` and `chars`.
+
+[OwnedString] and [PStr] compare and hash by Unicode scalar value, so they sort like [str] and hash the same as the
+equivalent [str], on every platform.  Data that isn't valid Unicode sorts by code point (unpaired surrogates) or byte, and hashes as U+FFFD.
 
 Platforms may have additional types specific to the platform, as needed.
+
+# Backends
+
+* On macOS, strings are `NSString`.
+* On Windows, strings are null-terminated UTF-16.
+* On Linux and other unix platforms, strings are null-terminated UTF-8.
+
+The `utf16-backend` feature also builds the Windows backend on Linux and other unix platforms, as [utf16_backend] (except for `HSTRING`
+support, which requires Windows), so that its conversion logic can be tested and benchmarked there.  It doesn't change
+the native backend.
  */
 #[cfg(target_os = "macos")]
 mod macos;
@@ -29,4 +56,96 @@ pub use macos::*;
 #[cfg(target_os = "windows")]
 mod windows;
 #[cfg(target_os = "windows")]
-pub use self::windows::*;
\ No newline at end of file
+pub use self::windows::*;
+/**
+The Windows UTF-16 backend, built alongside the native backend for testing and benchmarking.
+
+This has the same API as the native backend on Windows, except for `HSTRING` support and integration with the
+cross-platform types in [pcore::string](self), such as [SharedString].  Its [pstr!](crate::pstr) equivalent is
+[utf16_pstr!](crate::utf16_pstr).
+*/
+#[cfg(all(feature = "utf16-backend", not(any(target_os = "windows", target_os = "macos"))))]
+#[path = "string/windows.rs"]
+pub mod utf16_backend;
+//helpers shared by the backends that store UTF-16
+#[cfg(any(target_os = "macos", target_os = "windows", feature = "utf16-backend"))]
+mod utf16;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod linux;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use linux::*;
+mod buffer;
+pub use buffer::*;
+mod shared;
+pub use shared::*;
+mod maybe_owned;
+pub use maybe_owned::*;
+mod atom;
+pub use atom::*;
+mod array;
+pub use array::*;
+mod transcode;
+
+/**
+Formats like [format!], but straight into an [OwnedString] in the platform's encoding, without an intermediate [String] on Windows.
+
+A format string without arguments skips the formatting machinery; on macOS, its static text is used in place like [pstr!](crate::pstr).
+
+Panics if [OwnedString::try_format] would fail, for example because the result contains a null.
+
+```
+use pcore::pformat;
+let name = "world";
+assert_eq!(pformat!("hello {}", name), "hello world");
+```
+*/
+#[macro_export]
+macro_rules! pformat {
+    ($($arg:tt)*) => {
+        pcore::string::OwnedString::format(format_args!($($arg)*))
+    }
+}
+///Lets a `hashbrown` map keyed by [OwnedString] be queried with a [str], without converting it.
+///
+/// This works because [OwnedString] hashes the same as the equivalent [str].
+///
+/// ```
+/// # #[cfg(feature = "hashbrown")] {
+/// use pcore::release_pool::autoreleasepool;
+/// use pcore::string::OwnedString;
+/// autoreleasepool(|pool| {
+///     let mut map = hashbrown::HashMap::new();
+///     map.insert(OwnedString::new("name", pool), 1);
+///     assert_eq!(map.get("name"), Some(&1));
+/// })
+/// # }
+/// ```
+#[cfg(feature = "hashbrown")]
+impl hashbrown::Equivalent<OwnedString> for str {
+    fn equivalent(&self, key: &OwnedString) -> bool {
+        key == self
+    }
+}
+///Lets a `hashbrown` map keyed by [PStr] be queried with a [str], without converting it.
+#[cfg(feature = "hashbrown")]
+impl hashbrown::Equivalent<PStr> for str {
+    fn equivalent(&self, key: &PStr) -> bool {
+        key == self
+    }
+}
+
+#[cfg(feature = "hashbrown")]
+#[test] fn hashbrown_lookup() {
+    use crate::release_pool::autoreleasepool;
+    autoreleasepool(|pool| {
+        let mut map = hashbrown::HashMap::new();
+        map.insert(OwnedString::new("héllo", pool), 1);
+        map.insert(OwnedString::new(&*"x".repeat(300), pool), 2);
+        assert_eq!(map.get("héllo"), Some(&1));
+        assert_eq!(map.get(&*"x".repeat(300)), Some(&2));
+        assert_eq!(map.get("hello"), None);
+        let mut map = hashbrown::HashMap::new();
+        map.insert(crate::pstr!("name"), 3);
+        assert_eq!(map.get("name"), Some(&3));
+    })
+}
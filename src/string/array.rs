@@ -0,0 +1,203 @@
+use super::BufferUnit;
+use crate::error::Error;
+use std::fmt::Formatter;
+
+/**
+A string with a fixed capacity, stored inline in the platform's encoding.
+
+An [ArrayString] holds up to `N` code units (see [BufferUnit]), plus a terminator.  Converting a `&ArrayString`
+into a [ParameterString](super::ParameterString) borrows that storage, so short strings can be built and passed
+to the platform without touching the heap.
+
+```
+use pcore::string::{ArrayString, IntoParameterString};
+use pcore::release_pool::autoreleasepool;
+use std::convert::TryFrom;
+use std::fmt::Write;
+let mut key = ArrayString::<32>::try_from("HKEY_").unwrap();
+write!(key, "{}", 42).unwrap();
+assert_eq!(key, "HKEY_42");
+assert!(ArrayString::<4>::try_from("too long").is_err());
+autoreleasepool(|pool| {
+    assert_eq!(&*(&key).into_parameter_string(pool), "HKEY_42");
+})
+```
+*/
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct ArrayString<const N: usize> {
+    units: [BufferUnit; N],
+    //read as part of `units` when the string is full
+    terminator: BufferUnit,
+    len: usize,
+}
+
+#[cfg(target_os = "windows")]
+fn encode(s: &str) -> impl Iterator<Item=BufferUnit> + '_ {
+    s.encode_utf16()
+}
+#[cfg(not(target_os = "windows"))]
+fn encode(s: &str) -> impl Iterator<Item=BufferUnit> + '_ {
+    s.bytes()
+}
+///Encodes `s` into the start of `dst`, and returns the number of units written, or `None` if it doesn't fit.
+#[cfg(target_os = "windows")]
+fn encode_into(s: &str, dst: &mut [BufferUnit]) -> Option<usize> {
+    if super::transcode::utf16_len(s) > dst.len() {
+        return None;
+    }
+    Some(super::transcode::encode_utf16_into(s, dst))
+}
+#[cfg(not(target_os = "windows"))]
+fn encode_into(s: &str, dst: &mut [BufferUnit]) -> Option<usize> {
+    dst.get_mut(..s.len())?.copy_from_slice(s.as_bytes());
+    Some(s.len())
+}
+
+impl<const N: usize> ArrayString<N> {
+    ///Creates an empty string.
+    pub const fn new() -> Self {
+        ArrayString { units: [0; N], terminator: 0, len: 0 }
+    }
+    ///The most code units the string can hold, not counting the terminator.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    ///The length of the string in code units, not counting the terminator.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    ///Whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    ///Empties the string.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.units_mut_with_nul()[0] = 0;
+    }
+    ///Appends `s`, or fails without changing anything if it doesn't fit.
+    ///
+    /// Also fails if `s` contains a null, except on macOS.
+    pub fn try_push_str(&mut self, s: &str) -> Result<(), Error> {
+        #[cfg(not(target_os = "macos"))]
+        if s.as_bytes().contains(&0) {
+            return Err(Error::interior_nul());
+        }
+        let len = self.len;
+        let written = encode_into(s, &mut self.units[len..]).ok_or_else(Error::buffer_too_small)?;
+        self.len += written;
+        self.units_mut_with_nul()[len + written] = 0;
+        Ok(())
+    }
+    ///Appends `c`, or fails without changing anything if it doesn't fit.
+    pub fn try_push(&mut self, c: char) -> Result<(), Error> {
+        self.try_push_str(c.encode_utf8(&mut [0; 4]))
+    }
+    ///The string's code units, without the terminator.
+    pub(super) fn units(&self) -> &[BufferUnit] {
+        &self.units[..self.len]
+    }
+    ///The string's code units, with the terminator.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(super) fn units_with_nul(&self) -> &[BufferUnit] {
+        //`terminator` directly follows `units`, since the struct is repr(C) and they have the same alignment
+        unsafe{std::slice::from_raw_parts(self as *const Self as *const BufferUnit, self.len + 1)}
+    }
+    fn units_mut_with_nul(&mut self) -> &mut [BufferUnit] {
+        unsafe{std::slice::from_raw_parts_mut(self as *mut Self as *mut BufferUnit, N + 1)}
+    }
+    fn chars(&self) -> impl Iterator<Item=char> + '_ {
+        //the units were encoded from Rust strings, so they're valid
+        #[cfg(target_os = "windows")]
+        return char::decode_utf16(self.units().iter().copied()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER));
+        #[cfg(not(target_os = "windows"))]
+        return std::str::from_utf8(self.units()).unwrap_or_default().chars();
+    }
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        ArrayString::new()
+    }
+}
+impl<const N: usize> std::convert::TryFrom<&str> for ArrayString<N> {
+    type Error = Error;
+    ///Fails if `s` doesn't fit.
+    fn try_from(s: &str) -> Result<Self, Error> {
+        let mut string = ArrayString::new();
+        string.try_push_str(s)?;
+        Ok(string)
+    }
+}
+///Fails if the text doesn't fit.
+impl<const N: usize> std::fmt::Write for ArrayString<N> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.try_push_str(s).map_err(|_| std::fmt::Error)
+    }
+}
+impl<const N: usize> std::fmt::Display for ArrayString<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+impl<const N: usize> std::fmt::Debug for ArrayString<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.chars().collect::<String>(), f)
+    }
+}
+impl<const N: usize> PartialEq for ArrayString<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.units() == other.units()
+    }
+}
+impl<const N: usize> Eq for ArrayString<N> {}
+impl<const N: usize> PartialEq<str> for ArrayString<N> {
+    fn eq(&self, other: &str) -> bool {
+        self.units().iter().copied().eq(encode(other))
+    }
+}
+impl<const N: usize> PartialEq<&str> for ArrayString<N> {
+    fn eq(&self, other: &&str) -> bool {
+        self.units().iter().copied().eq(encode(other))
+    }
+}
+
+#[test] fn capacity() {
+    use std::convert::TryFrom;
+    use std::fmt::Write;
+    let mut string = ArrayString::<5>::try_from("hell").unwrap();
+    assert_eq!(string.len(), 4);
+    assert!(string.try_push_str("o!").is_err());
+    assert_eq!(string, "hell");
+    assert_eq!(string.units_with_nul().last(), Some(&0));
+    string.try_push('o').unwrap();
+    assert_eq!(string, "hello");
+    assert_eq!(string.units_with_nul().len(), 6);
+    assert_eq!(string.units_with_nul().last(), Some(&0));
+    assert!(write!(string, "!").is_err());
+    assert_eq!(string.to_string(), "hello");
+    assert_eq!(format!("{:?}", string), "\"hello\"");
+    string.clear();
+    assert!(string.is_empty());
+    assert_eq!(string.units_with_nul(), [0]);
+    write!(string, "{}", 12).unwrap();
+    assert_eq!(string, "12");
+    assert!(ArrayString::<5>::try_from("hello!").is_err());
+    assert_eq!(ArrayString::<8>::try_from("héllo").unwrap().to_string(), "héllo");
+    #[cfg(not(target_os = "macos"))]
+    assert!(ArrayString::<8>::try_from("a\0b").is_err());
+}
+
+#[test] fn borrows_storage() {
+    use super::IntoParameterString;
+    use std::convert::TryFrom;
+    crate::release_pool::autoreleasepool(|pool| {
+        let string = ArrayString::<16>::try_from("héllo").unwrap();
+        let before = pool.innermost_statistics().conversions;
+        let parameter_string = (&string).into_parameter_string(pool);
+        assert_eq!(&*parameter_string, "héllo");
+        assert_eq!(pool.innermost_statistics().conversions, before);
+    })
+}
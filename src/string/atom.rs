@@ -0,0 +1,174 @@
+use super::{IntoParameterString, NativeStr, OwnedString};
+use crate::error::Error;
+use crate::release_pool::autoreleasepool;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt::Formatter;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+/**
+An interned platform string.
+
+Each distinct string is stored once, in the platform's encoding, for the life of the process.  An [Atom] is a
+`Copy` handle to it, so comparing and hashing atoms takes constant time, and converting one into a
+[ParameterString](super::ParameterString) doesn't copy or convert anything.
+
+Atoms can be created from any thread.  Since interned strings are never freed, this suits a bounded set of
+identifiers, like event names or property keys, rather than arbitrary text.
+
+```
+use pcore::string::Atom;
+let clicked = Atom::new("clicked");
+assert_eq!(clicked, Atom::new("clicked"));
+assert_ne!(clicked, Atom::new("closed"));
+assert_eq!(clicked, "clicked");
+```
+*/
+#[derive(Clone, Copy)]
+pub struct Atom(pub(super) &'static NativeStr);
+
+//the NSString is never mutated once it's interned, and immutable Foundation strings are thread-safe
+#[cfg(target_os = "macos")]
+unsafe impl Send for Atom {}
+#[cfg(target_os = "macos")]
+unsafe impl Sync for Atom {}
+
+struct Interner {
+    hasher: RandomState,
+    //atoms, by the hash of their text
+    buckets: HashMap<u64, Vec<Atom>>,
+}
+impl Interner {
+    fn find(&self, hash: u64, string: &str) -> Option<Atom> {
+        self.buckets.get(&hash)?.iter().copied().find(|atom| *atom.0 == *string)
+    }
+}
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(|| RwLock::new(Interner { hasher: RandomState::new(), buckets: HashMap::new() }))
+}
+
+impl Atom {
+    ///Interns `string`, or returns the existing atom for it.
+    ///
+    /// Panics if [Self::try_new] would fail.
+    pub fn new(string: &str) -> Atom {
+        Atom::try_new(string).unwrap()
+    }
+    ///Interns `string`, or returns the existing atom for it.  Fails if the string can't be converted, for example because it contains a null.
+    pub fn try_new(string: &str) -> Result<Atom, Error> {
+        let hash = {
+            let interner = interner().read().unwrap_or_else(PoisonError::into_inner);
+            let hash = interner.hasher.hash_one(string);
+            if let Some(atom) = interner.find(hash, string) {
+                return Ok(atom);
+            }
+            hash
+        };
+        //convert outside the lock
+        let owned = autoreleasepool(|pool| string.try_into_parameter_string(pool).map(|string| OwnedString::new(string, pool)))?;
+        let mut interner = interner().write().unwrap_or_else(PoisonError::into_inner);
+        //another thread may have interned it in the meantime
+        if let Some(atom) = interner.find(hash, string) {
+            return Ok(atom);
+        }
+        let atom = Atom(Box::leak(Box::new(owned)));
+        interner.buckets.entry(hash).or_default().push(atom);
+        Ok(atom)
+    }
+    ///The interned string, which lives for the rest of the process.
+    pub fn as_native_str(self) -> &'static NativeStr {
+        self.0
+    }
+}
+
+impl Deref for Atom {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        self.0
+    }
+}
+impl AsRef<NativeStr> for Atom {
+    fn as_ref(&self) -> &NativeStr {
+        self.0
+    }
+}
+impl std::fmt::Display for Atom {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self.0, f)
+    }
+}
+impl std::fmt::Debug for Atom {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.0, f)
+    }
+}
+///Compares by identity, which is equivalent to comparing the text since each string is interned once.
+impl PartialEq for Atom {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+impl Eq for Atom {}
+///Hashes by identity.  Unlike other pcore strings, this isn't the same as the hash of the equivalent [str].
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 as *const NativeStr as *const u8).hash(state)
+    }
+}
+impl PartialOrd for Atom {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for Atom {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            return Ordering::Equal;
+        }
+        self.0.cmp(other.0)
+    }
+}
+impl PartialEq<str> for Atom {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+impl PartialEq<&str> for Atom {
+    fn eq(&self, other: &&str) -> bool {
+        *self.0 == **other
+    }
+}
+
+#[test] fn interning() {
+    let a = Atom::new("héllo");
+    assert_eq!(a, Atom::new("héllo"));
+    assert!(std::ptr::eq(a.as_native_str(), Atom::new("héllo").as_native_str()));
+    assert_ne!(a, Atom::new("hello"));
+    assert_eq!(a, "héllo");
+    assert!(Atom::new("a") < Atom::new("b"));
+    #[cfg(not(target_os = "macos"))]
+    assert!(Atom::try_new("a\0b").is_err());
+    autoreleasepool(|pool| {
+        let before = pool.innermost_statistics().conversions;
+        assert_eq!(&*a.into_parameter_string(pool), "héllo");
+        assert_eq!(pool.innermost_statistics().conversions, before);
+    })
+}
+
+#[test] fn threads() {
+    let names: Vec<String> = (0..64).map(|i| format!("atom-{}", i)).collect();
+    let threads: Vec<_> = (0..4).map(|_| {
+        let names = names.clone();
+        std::thread::spawn(move || names.iter().map(|name| Atom::new(name)).collect::<Vec<_>>())
+    }).collect();
+    let results: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+    for atoms in &results {
+        assert_eq!(atoms, &results[0]);
+    }
+    assert_eq!(results[0][3], "atom-3");
+}
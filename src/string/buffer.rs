@@ -0,0 +1,256 @@
+use super::OwnedString;
+use crate::error::Error;
+
+#[cfg(not(target_os = "macos"))]
+use super::NativeUnit as Unit;
+#[cfg(target_os = "macos")]
+type Unit = u8;
+
+///The code unit [StringBuffer] hands to its closure.
+///
+/// This is `u16` (UTF-16) on Windows and `u8` elsewhere: null-terminated UTF-8 on Linux, and UTF-8 that is
+/// validated when the string is finished on macOS.
+pub type BufferUnit = Unit;
+
+///What happened when a [StringBuffer] closure tried to fill its buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    ///The string fit, and is this many units long, not counting any terminator.
+    Complete(usize),
+    ///The string didn't fit, and the API didn't say how much space it needs.  The buffer grows according to its [Growth] policy.
+    Truncated,
+    ///The string didn't fit, and needs a buffer of this many units, not counting any terminator.
+    NeedsCapacity(usize),
+}
+
+///How a [StringBuffer] grows after [Fill::Truncated].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Growth {
+    ///Doubles the capacity.
+    Double,
+    ///Adds this many units to the capacity.
+    Add(usize),
+}
+
+/**
+Calls an API that fills caller-provided memory, growing the buffer and retrying until the string fits.
+
+The closure gets a buffer of native [BufferUnit]s, and reports what happened as a [Fill].  A terminator slot
+is reserved past the end of the buffer, so finishing the string never grows it.  On Windows and Linux, the finished
+[OwnedString] takes over the buffer, spare capacity included, rather than being copied out of it.
+
+```
+use pcore::string::{StringBuffer, Fill};
+let string = StringBuffer::new().fill(|buffer| {
+    //stands in for an API that writes "hi"
+    if buffer.len() < 2 {
+        return Ok(Fill::NeedsCapacity(2));
+    }
+    buffer[0] = b'h'.into();
+    buffer[1] = b'i'.into();
+    Ok(Fill::Complete(2))
+}).unwrap();
+assert_eq!(string, "hi");
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StringBuffer {
+    capacity: usize,
+    max_capacity: usize,
+    growth: Growth,
+}
+
+impl StringBuffer {
+    ///A buffer of 256 units that doubles on truncation, up to 64Ki units.
+    pub const fn new() -> Self {
+        StringBuffer { capacity: 256, max_capacity: 64 * 1024, growth: Growth::Double }
+    }
+    ///Sets the capacity of the first attempt, in units.
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+    ///Sets the largest capacity to try, in units.  If the string doesn't fit, [Self::fill] fails.
+    pub const fn max_capacity(mut self, max_capacity: usize) -> Self {
+        self.max_capacity = max_capacity;
+        self
+    }
+    ///Sets how the buffer grows after [Fill::Truncated].
+    pub const fn growth(mut self, growth: Growth) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    ///Calls `f` until the string fits, and returns it.
+    ///
+    /// Errors from `f` are returned as-is.  Fails if the string would need more than the maximum capacity,
+    /// or contains a null before its end.  On macOS, also fails if the string isn't valid UTF-8.
+    pub fn fill<F: FnMut(&mut [BufferUnit]) -> Result<Fill, Error>>(self, mut f: F) -> Result<OwnedString, Error> {
+        let mut capacity = self.capacity.min(self.max_capacity);
+        let mut buffer = Vec::new();
+        loop {
+            //one more for the terminator
+            buffer.clear();
+            buffer.try_reserve_exact(capacity + 1).map_err(|_| Error::out_of_memory())?;
+            buffer.resize(capacity + 1, 0);
+            let next = match f(&mut buffer[..capacity])? {
+                Fill::Complete(len) => {
+                    assert!(len <= capacity, "Fill::Complete({}) is longer than the buffer ({})", len, capacity);
+                    buffer.truncate(len);
+                    return finish(buffer);
+                }
+                Fill::NeedsCapacity(needed) if needed > capacity => {
+                    if needed > self.max_capacity {
+                        return Err(Error::buffer_too_small());
+                    }
+                    needed
+                }
+                //an API that asks for no more than it had can't be trusted, so grow anyway
+                Fill::NeedsCapacity(_) | Fill::Truncated => {
+                    if capacity >= self.max_capacity {
+                        return Err(Error::buffer_too_small());
+                    }
+                    let grown = match self.growth {
+                        Growth::Double => capacity.saturating_mul(2).max(1),
+                        Growth::Add(units) => capacity.saturating_add(units.max(1)),
+                    };
+                    grown.min(self.max_capacity)
+                }
+            };
+            capacity = next;
+        }
+    }
+}
+
+impl Default for StringBuffer {
+    fn default() -> Self {
+        StringBuffer::new()
+    }
+}
+
+///Turns the units of a filled buffer, without terminator, into a string.
+#[cfg(not(target_os = "macos"))]
+fn finish(mut buffer: Vec<BufferUnit>) -> Result<OwnedString, Error> {
+    if buffer.contains(&0) {
+        return Err(Error::interior_nul());
+    }
+    //the terminator slot was reserved, so this doesn't reallocate
+    buffer.push(0);
+    Ok(OwnedString::from_vec_with_nul(buffer))
+}
+#[cfg(target_os = "macos")]
+fn finish(buffer: Vec<BufferUnit>) -> Result<OwnedString, Error> {
+    if buffer.contains(&0) {
+        return Err(Error::interior_nul());
+    }
+    let string = String::from_utf8(buffer).map_err(|_| Error::invalid_encoding())?;
+    Ok(OwnedString::from_string(string))
+}
+
+//BufferUnit is u8 on some platforms
+#[cfg(test)]
+#[allow(clippy::useless_conversion)]
+fn unit(ascii: u8) -> BufferUnit {
+    ascii.into()
+}
+
+#[test] fn grows() {
+    //writes 10 units, pretending not to know how many it needs
+    let mut attempts = Vec::new();
+    let string = StringBuffer::new().with_capacity(1).fill(|buffer| {
+        attempts.push(buffer.len());
+        if buffer.len() < 10 {
+            return Ok(Fill::Truncated);
+        }
+        for u in &mut buffer[..10] {
+            *u = unit(b'a');
+        }
+        Ok(Fill::Complete(10))
+    }).unwrap();
+    assert_eq!(string, "aaaaaaaaaa");
+    assert_eq!(attempts, [1, 2, 4, 8, 16]);
+
+    let mut attempts = Vec::new();
+    StringBuffer::new().with_capacity(1).growth(Growth::Add(4)).fill(|buffer| {
+        attempts.push(buffer.len());
+        Ok(if buffer.len() < 10 { Fill::Truncated } else { Fill::Complete(0) })
+    }).unwrap();
+    assert_eq!(attempts, [1, 5, 9, 13]);
+
+    let mut attempts = Vec::new();
+    StringBuffer::new().with_capacity(1).fill(|buffer| {
+        attempts.push(buffer.len());
+        Ok(if buffer.len() < 300 { Fill::NeedsCapacity(300) } else { Fill::Complete(0) })
+    }).unwrap();
+    assert_eq!(attempts, [1, 300]);
+}
+
+#[test] fn limits() {
+    let mut attempts = Vec::new();
+    let result = StringBuffer::new().with_capacity(1).max_capacity(6).fill(|buffer| {
+        attempts.push(buffer.len());
+        Ok(Fill::Truncated)
+    });
+    assert!(result.is_err());
+    assert_eq!(attempts, [1, 2, 4, 6]);
+    assert!(StringBuffer::new().max_capacity(6).fill(|_| Ok(Fill::NeedsCapacity(7))).is_err());
+    assert!(StringBuffer::new().fill(|buffer| {
+        buffer[0] = unit(b'a');
+        buffer[1] = 0;
+        buffer[2] = unit(b'b');
+        Ok(Fill::Complete(3))
+    }).is_err());
+}
+
+#[cfg(not(target_os = "macos"))]
+#[test] fn finishes_in_place() {
+    let mut ptr = std::ptr::null();
+    let string = StringBuffer::new().with_capacity(16).fill(|buffer| {
+        ptr = buffer.as_ptr();
+        buffer[0] = unit(b'h');
+        buffer[1] = unit(b'i');
+        Ok(Fill::Complete(2))
+    }).unwrap();
+    assert_eq!(string, "hi");
+    #[cfg(target_os = "windows")]
+    assert_eq!(string.u16z_view().utf16z_slice().as_ptr(), ptr);
+    #[cfg(not(target_os = "windows"))]
+    assert_eq!(string.c_str_view().as_ptr() as *const BufferUnit, ptr);
+}
+
+#[cfg(target_os = "linux")]
+#[test] fn readlink() {
+    use std::ffi::CStr;
+    let path = CStr::from_bytes_with_nul(b"/proc/self/exe\0").unwrap();
+    let exe = StringBuffer::new().with_capacity(1).fill(|buffer| {
+        let len = unsafe{libc::readlink(path.as_ptr(), buffer.as_mut_ptr() as *mut libc::c_char, buffer.len())};
+        if len < 0 {
+            Err(Error::errno_last())
+        }
+        //readlink truncates without saying so
+        else if len as usize == buffer.len() {
+            Ok(Fill::Truncated)
+        }
+        else {
+            Ok(Fill::Complete(len as usize))
+        }
+    }).unwrap();
+    assert_eq!(std::path::PathBuf::from(exe.to_os_string()), std::env::current_exe().unwrap());
+}
+
+#[cfg(target_os = "linux")]
+#[test] fn getcwd() {
+    let cwd = StringBuffer::new().with_capacity(1).fill(|buffer| {
+        let result = unsafe{libc::getcwd(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len())};
+        if !result.is_null() {
+            Ok(Fill::Complete(buffer.iter().position(|&b| b == 0).unwrap()))
+        }
+        else {
+            match Error::errno_last() {
+                e if *e.as_errno() == libc::ERANGE => Ok(Fill::Truncated),
+                e => Err(e),
+            }
+        }
+    }).unwrap();
+    assert_eq!(std::path::PathBuf::from(cwd.to_os_string()), std::env::current_dir().unwrap());
+}
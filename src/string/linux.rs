@@ -0,0 +1,1314 @@
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::fmt::{Formatter, Write};
+use std::borrow::{Borrow, Cow};
+use std::ops::Deref;
+use std::hash::{Hash, Hasher};
+use std::cmp::Ordering;
+use std::os::raw::c_char;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use crate::release_pool::ReleasePool;
+use crate::error::Error;
+use crate::release_pool::arena::ArenaSlice;
+use crate::release_pool::stack::{defer_drop, record_conversion};
+use super::{ArrayString, Atom, MaybeOwnedString, SharedString};
+use super::transcode;
+
+///The code unit of this backend's native encoding.
+pub(crate) type NativeUnit = u8;
+
+/**
+A type that erases [IntoParameterString] into a concrete type with a named lifetime.
+
+This type is appropriate for use in a builder pattern, or other cases where the string
+will be stored for a short time.
+
+# Example
+
+```
+use pcore::string::{ParameterString,IntoParameterString};
+use pcore::release_pool::ReleasePool;
+struct StringBuilder<'a> {
+     inner: ParameterString<'a>,
+}
+impl<'a> StringBuilder<'a> {
+    fn new<S: IntoParameterString<'a>>(string: S, pool: &ReleasePool) -> Self {
+        Self { inner: string.into_parameter_string(pool) }
+    }
+}
+```
+# Implementation
+
+On Linux, OS APIs take null-terminated byte strings, which are UTF-8 by convention.  This type contains a slice of
+null-terminated bytes, followed by owned storage (if needed).  To implement borrowed types, storage can be set to `None`.
+
+Owned storage is usually a heap-allocated vector, but conversions performed under a [ReleasePool] with an arena
+(see [ReleasePool::with_arena]) store their bytes in the arena instead.
+ */
+#[derive(Debug)]
+pub struct ParameterString<'a>(&'a [u8],Option<Storage>);
+
+///Owned storage backing a [ParameterString].
+#[derive(Debug)]
+enum Storage {
+    //keeps any spare capacity, rather than reallocating to shrink it
+    Heap(Vec<u8>),
+    //only held to keep the arena chunk alive
+    Arena(#[allow(dead_code)] ArenaSlice<u8>),
+    Shared(Arc<OwnedString>),
+}
+
+//more or less, ParameterString gets its trait implementations from the `.0` field
+impl<'a> PartialEq for ParameterString<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl<'a> Eq for ParameterString<'a> {}
+
+///Hashes the same as the equivalent [str].
+impl<'a> Hash for ParameterString<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.units(), state)
+    }
+}
+
+impl<'a> IntoParameterString<'a> for ParameterString<'a> {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        self
+    }
+}
+
+impl<'a> ParameterString<'a> {
+    ///Copies `bytes` and a null terminator into the pool's arena if it has one and the arena has room, otherwise onto the heap.
+    fn try_copy_with_nul(bytes: &[u8], pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        if bytes.contains(&0) {
+            return Err(Error::interior_nul());
+        }
+        let arena_slice = pool.arena().and_then(|arena| arena.alloc_with(bytes.len() + 1, |buffer| {
+            buffer[..bytes.len()].copy_from_slice(bytes);
+            buffer[bytes.len()] = 0;
+            bytes.len() + 1
+        }));
+        if let Some(arena_slice) = arena_slice {
+            pool.record_deferred(1);
+            pool.record_deferred_bytes(arena_slice.as_slice().len());
+            //fool rust into letting us take &temp
+            let slice = unsafe{std::slice::from_raw_parts(arena_slice.as_slice().as_ptr(), arena_slice.as_slice().len())};
+            return Ok(ParameterString(slice, Some(Storage::Arena(arena_slice))));
+        }
+        let mut v = Vec::new();
+        v.try_reserve_exact(bytes.len() + 1).map_err(|_| Error::out_of_memory())?;
+        v.extend_from_slice(bytes);
+        v.push(0);
+        Ok(ParameterString::from_vec_with_nul(v))
+    }
+    ///Appends a null terminator to `v`, reusing its allocation where capacity allows.
+    fn try_from_vec(v: Vec<u8>) -> Result<ParameterString<'static>, Error> {
+        try_push_nul(v).map(ParameterString::from_vec_with_nul)
+    }
+    ///Takes ownership of `v`, which must be null-terminated.
+    fn from_vec_with_nul(v: Vec<u8>) -> ParameterString<'static> {
+        //fool rust into letting us take &temp
+        let slice = unsafe{std::slice::from_raw_parts(v.as_ptr(), v.len())};
+        ParameterString(slice, Some(Storage::Heap(v)))
+    }
+    ///Takes the string's bytes without the null terminator, reusing its storage if it owns a vector.
+    fn into_vec(self) -> Vec<u8> {
+        match self {
+            ParameterString(_, Some(Storage::Heap(mut v))) => {
+                v.pop();
+                v
+            }
+            other => other.units().to_vec(),
+        }
+    }
+    fn units(&self) -> &[u8] {
+        self.0.split_last().unwrap().1
+    }
+}
+
+//read-back implementations shared by the string types.  `units` excludes the null terminator.
+fn chars(units: &[u8]) -> impl Iterator<Item=char> + '_ {
+    //this matches the replacement behavior of `String::from_utf8_lossy`
+    units.utf8_chunks().flat_map(|chunk| {
+        let replacement = if chunk.invalid().is_empty() { None } else { Some(char::REPLACEMENT_CHARACTER) };
+        chunk.valid().chars().chain(replacement)
+    })
+}
+fn to_string_lossy(units: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(units)
+}
+fn try_to_string(units: &[u8]) -> Result<String, Error> {
+    std::str::from_utf8(units).map(|s| s.to_owned()).map_err(|_| Error::invalid_encoding())
+}
+fn len_utf8(units: &[u8]) -> usize {
+    match std::str::from_utf8(units) {
+        Ok(s) => s.len(),
+        Err(_) => chars(units).map(char::len_utf8).sum(),
+    }
+}
+fn len_utf16(units: &[u8]) -> usize {
+    match std::str::from_utf8(units) {
+        Ok(s) => transcode::utf16_len(s),
+        Err(_) => chars(units).map(char::len_utf16).sum(),
+    }
+}
+///Hashes the same as the equivalent `str`, replacing invalid data with U+FFFD.
+fn hash<H: Hasher>(units: &[u8], state: &mut H) {
+    //this only allocates for invalid data
+    to_string_lossy(units).hash(state)
+}
+///Writes the string, replacing invalid data with U+FFFD.
+fn fmt_lossy(units: &[u8], f: &mut Formatter<'_>) -> std::fmt::Result {
+    for chunk in units.utf8_chunks() {
+        f.write_str(chunk.valid())?;
+        if !chunk.invalid().is_empty() {
+            f.write_char(char::REPLACEMENT_CHARACTER)?;
+        }
+    }
+    Ok(())
+}
+///Writes the string, escaping invalid bytes as `\xff`.
+fn fmt_escaped(units: &[u8], f: &mut Formatter<'_>) -> std::fmt::Result {
+    for chunk in units.utf8_chunks() {
+        f.write_str(chunk.valid())?;
+        for byte in chunk.invalid() {
+            write!(f, "\\x{:02x}", byte)?;
+        }
+    }
+    Ok(())
+}
+
+///Type that can be converted into a platform string parameter.
+///
+/// The methods of this trait is platform-specific, so don't use them in cross-platform code.
+/// The type itself however, is available everywhere.
+///
+/// Generally you want to accept a generic parameter of the form `<S: IntoParameterString>`, for example
+///
+///```
+/// use pcore::string::IntoParameterString;
+/// fn foo<'a, S: IntoParameterString<'a>>(s: S) {
+///    //use `s`
+/// }
+/// ```
+///
+/// This trait is implemented by various standard library types ([str], [String], etc.) but also the output of [pstr!],
+/// platform-specific string types, and various others.  Any of these conforming types may be passed to the function directly.
+/// Encoding or conversion will be performed automatically if required.
+///
+/// For best performance, prefer passing a value of:
+/// 1.  [PStr], if the string can be known at compile-time
+/// 2.  [IntoParameterString], if one is available
+/// 3.  A platform-specific type, such as the result of calling an OS API.
+/// 4.  A type with the native encoding, such as null-terminated UTF-8 (on Linux), etc.
+/// 5.  A standard library type, like [String].
+///
+pub trait IntoParameterString<'a> {
+    ///Converts into a null-terminated C string.
+    ///
+    /// If the string has to be converted, the converted storage is deferred into the innermost pool on this thread.
+    ///
+    /// # Safety
+    /// * The resulting pointer will be valid only
+    /// * For the lifetime of the `self` parameter, and until the innermost pool on this thread is drained or dropped
+    /// * When the underlying string is not modified.  e.g., you must pass it to a function taking `const char *`
+    ///
+    /// # Panics
+    /// If the string has to be converted, and no pool is active on this thread.
+    unsafe fn into_unsafe_const_cstr(self) -> *const c_char where Self: Sized {
+        //not needed on linux
+        let pool = ReleasePool::assuming_pool();
+        let parameter_string = self.into_parameter_string(pool);
+        let ptr = parameter_string.0.as_ptr() as *const c_char;
+        if let Some(storage) = parameter_string.1 {
+            //the heap or arena storage doesn't move, so the pointer stays valid
+            defer_drop(storage);
+        }
+        ptr
+    }
+
+    ///Converts into an erased type
+    ///
+    /// For compatibility with macOS, this takes a releasepool parameter
+    ///
+    /// # Panics
+    /// If [try_into_parameter_string](Self::try_into_parameter_string) would fail.
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a>;
+
+    ///Converts into an erased type, or fails if the string can't be converted.
+    ///
+    /// Conversions fail if the string contains an interior null (which would truncate it), can't be encoded natively,
+    /// or storage for the converted string can't be allocated.
+    ///
+    /// Types whose conversion can't fail use this default implementation, which calls [into_parameter_string](Self::into_parameter_string).
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> where Self: Sized {
+        Ok(self.into_parameter_string(pool))
+    }
+}
+
+///Appends a null terminator to `v`, or fails if it already contains one.
+fn try_push_nul(mut v: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if v.contains(&0) {
+        return Err(Error::interior_nul());
+    }
+    v.try_reserve_exact(1).map_err(|_| Error::out_of_memory())?;
+    v.push(0);
+    Ok(v)
+}
+///Implements conversions, by copying to append a null terminator
+impl<'a> IntoParameterString<'a> for &'a str {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        ParameterString::try_copy_with_nul(self.as_bytes(), pool)
+    }
+}
+///Appends a null terminator, reusing the string's allocation where capacity allows
+impl IntoParameterString<'static> for String {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, _pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        ParameterString::try_from_vec(self.into_bytes())
+    }
+}
+///Formats straight into a null-terminated buffer.  A format string without arguments is copied, without the formatting machinery.
+impl<'a> IntoParameterString<'a> for std::fmt::Arguments<'a> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        match self.as_str() {
+            Some(piece) => ParameterString::try_copy_with_nul(piece.as_bytes(), pool),
+            None => ParameterString::try_from_vec(std::fmt::format(self).into_bytes()),
+        }
+    }
+}
+impl IntoParameterString<'static> for OsString {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, _pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        ParameterString::try_from_vec(self.into_vec())
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a OsStr {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        ParameterString::try_copy_with_nul(self.as_bytes(), pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a Path {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.as_os_str().into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        self.as_os_str().try_into_parameter_string(pool)
+    }
+}
+impl IntoParameterString<'static> for PathBuf {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.into_os_string().into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        self.into_os_string().try_into_parameter_string(pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a String {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.as_str().into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        self.as_str().try_into_parameter_string(pool)
+    }
+}
+impl IntoParameterString<'static> for Box<str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        String::from(self).into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        String::from(self).try_into_parameter_string(pool)
+    }
+}
+///Copies, since the allocation is shared
+impl IntoParameterString<'static> for Rc<str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        ParameterString::try_copy_with_nul(self.as_bytes(), pool)
+    }
+}
+///Copies, since the allocation is shared
+impl IntoParameterString<'static> for Arc<str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        ParameterString::try_copy_with_nul(self.as_bytes(), pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for Cow<'a, str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        match self {
+            Cow::Borrowed(s) => s.try_into_parameter_string(pool),
+            Cow::Owned(s) => s.try_into_parameter_string(pool),
+        }
+    }
+}
+///Borrows the string, which is already null-terminated
+impl<'a> IntoParameterString<'a> for &'a CStr {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(self.to_bytes_with_nul(), None)
+    }
+}
+///Takes ownership of the string, which is already null-terminated
+impl IntoParameterString<'static> for CString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'static> {
+        ParameterString::from_vec_with_nul(self.into_bytes_with_nul())
+    }
+}
+impl IntoParameterString<'static> for char {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        let mut buffer = [0; 4];
+        ParameterString::try_copy_with_nul(self.encode_utf8(&mut buffer).as_bytes(), pool)
+    }
+}
+
+///Represents a null-terminated string of length not known at runtime, such as a buffer filled in by the OS
+///
+/// Use [U8ZErasedLength::with_u8_unknown_length] or [U8ZErasedLength::from_ptr] for buffers that might not be terminated, such as ones
+/// from untrusted sources.  Finding the length of (or converting) such a buffer panics if it turns out to have no terminator;
+/// [U8ZErasedLength::find_length_within] never does.
+pub struct U8ZErasedLength<'a> {
+    bytes: &'a [u8],
+    //whether the caller promised a terminator, which the pointer fast path relies on
+    terminated: bool,
+}
+impl<'a> U8ZErasedLength<'a> {
+    ///# Safety
+    /// * The slice must have a null-terminator
+    pub unsafe fn with_u8_z_unknown_length(slice: &'a [u8]) -> Self {
+        Self { bytes: slice, terminated: true }
+    }
+    ///Wraps a slice that may or may not contain a null-terminator.
+    pub fn with_u8_unknown_length(slice: &'a [u8]) -> Self {
+        Self { bytes: slice, terminated: false }
+    }
+    ///Wraps a pointer to a string that may or may not be null-terminated within its first `max` bytes.
+    ///
+    /// Nothing past the terminator, or past `max` bytes, is read.
+    ///
+    /// # Safety
+    /// * `ptr` must be valid for reads up to and including its first null, or for `max` bytes if there is no null among them
+    /// * That memory must not be mutated for `'a`
+    pub unsafe fn from_ptr(ptr: *const c_char, max: usize) -> Self {
+        let len = libc::strnlen(ptr, max);
+        if len < max {
+            Self { bytes: std::slice::from_raw_parts(ptr as *const u8, len + 1), terminated: true }
+        }
+        else {
+            Self { bytes: std::slice::from_raw_parts(ptr as *const u8, len), terminated: false }
+        }
+    }
+    ///Finds the terminator within the first `max` bytes of the slice, or returns `None` if there isn't one.
+    ///
+    /// This doesn't rely on the slice being null-terminated, so it's safe to use on buffers from untrusted sources.
+    pub fn find_length_within(&self, max: usize) -> Option<&'a CStr> {
+        //scans a word at a time
+        CStr::from_bytes_until_nul(&self.bytes[..max.min(self.bytes.len())]).ok()
+    }
+    ///Finds the terminator.
+    ///
+    /// # Panics
+    /// If the slice isn't null-terminated.
+    pub fn find_length(&self) -> &'a CStr {
+        self.find_length_within(self.bytes.len()).expect("U8Z is not null-terminated")
+    }
+}
+impl<'a> std::fmt::Debug for U8ZErasedLength<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.find_length_within(self.bytes.len()) {
+            Some(c_str) => fmt_escaped(c_str.to_bytes(), f),
+            None => fmt_escaped(self.bytes, f),
+        }
+    }
+}
+impl<'a> IntoParameterString<'a> for &U8ZErasedLength<'a> {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(self.find_length().to_bytes_with_nul(), None)
+    }
+    unsafe fn into_unsafe_const_cstr(self) -> *const c_char where Self: Sized {
+        if self.terminated {
+            //faster path without finding out length
+            self.bytes.as_ptr() as *const c_char
+        }
+        else {
+            self.find_length().as_ptr()
+        }
+    }
+}
+
+/**
+A borrowed platform string, like [str] or [OsStr] for the platform's encoding.
+
+[OwnedString], [PStr] and [ParameterString] all dereference to it, so functions that only read a string can take
+a `&NativeStr` without consuming or converting anything.
+
+```
+use pcore::string::{NativeStr, OwnedString};
+use pcore::release_pool::autoreleasepool;
+fn shout(string: &NativeStr) -> String {
+    string.to_string_lossy().to_uppercase()
+}
+autoreleasepool(|pool| {
+    assert_eq!(shout(&OwnedString::new("hi", pool)), "HI");
+    assert_eq!(shout(&pcore::pstr!("hi")), "HI");
+})
+```
+*/
+#[repr(transparent)]
+pub struct NativeStr(
+    //null-terminated
+    [u8]
+);
+impl NativeStr {
+    ///Views `units`, which must be null-terminated.
+    fn from_units_with_nul(units: &[u8]) -> &NativeStr {
+        //NativeStr is a transparent wrapper around [u8]
+        unsafe{&*(units as *const [u8] as *const NativeStr)}
+    }
+    fn units(&self) -> &[u8] {
+        self.0.split_last().unwrap().1
+    }
+    ///A view into the string as a C string.
+    ///
+    /// If the string contains an interior null, the view ends there.
+    pub fn c_str_view(&self) -> &CStr {
+        CStr::from_bytes_until_nul(&self.0).unwrap()
+    }
+    ///Converts to a Rust string, replacing invalid data with U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        to_string_lossy(self.units())
+    }
+    ///Converts to a Rust string, or fails if the string contains invalid data.
+    pub fn try_to_string(&self) -> Result<String, Error> {
+        try_to_string(self.units())
+    }
+    ///The length of the string in UTF-8 code units (bytes).  Invalid data counts as U+FFFD.
+    pub fn len_utf8(&self) -> usize {
+        len_utf8(self.units())
+    }
+    ///The length of the string in UTF-16 code units.  Invalid data counts as U+FFFD.
+    pub fn len_utf16(&self) -> usize {
+        len_utf16(self.units())
+    }
+    ///Whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.units().is_empty()
+    }
+    ///Iterates over the characters of the string, replacing invalid data with U+FFFD.
+    pub fn chars(&self) -> impl Iterator<Item=char> + '_ {
+        chars(self.units())
+    }
+    ///Converts to an [OsString].  This is lossless, even for data that isn't UTF-8.
+    pub fn to_os_string(&self) -> OsString {
+        OsString::from_vec(self.units().to_vec())
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a NativeStr {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(&self.0, None)
+    }
+}
+impl ToOwned for NativeStr {
+    type Owned = OwnedString;
+    fn to_owned(&self) -> OwnedString {
+        OwnedString(self.0.to_vec())
+    }
+}
+impl AsRef<NativeStr> for NativeStr {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl std::fmt::Display for NativeStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.units(), f)
+    }
+}
+impl std::fmt::Debug for NativeStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.units(), f)
+    }
+}
+impl PartialEq for NativeStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl Eq for NativeStr {}
+///Hashes the same as the equivalent [str].
+impl Hash for NativeStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.units(), state)
+    }
+}
+impl PartialOrd for NativeStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].  UTF-8 byte order is also scalar value order.
+impl Ord for NativeStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.units().cmp(other.units())
+    }
+}
+impl PartialEq<str> for NativeStr {
+    fn eq(&self, other: &str) -> bool {
+        self.units() == other.as_bytes()
+    }
+}
+impl PartialEq<&str> for NativeStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.units() == other.as_bytes()
+    }
+}
+impl<'a> Deref for ParameterString<'a> {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_units_with_nul(self.0)
+    }
+}
+impl<'a> AsRef<NativeStr> for ParameterString<'a> {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Deref for PStr {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_units_with_nul(self.0)
+    }
+}
+impl AsRef<NativeStr> for PStr {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Deref for OwnedString {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_units_with_nul(&self.0)
+    }
+}
+impl AsRef<NativeStr> for OwnedString {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Borrow<NativeStr> for OwnedString {
+    fn borrow(&self) -> &NativeStr {
+        self
+    }
+}
+
+///Borrows the string's inline storage, without copying or converting it.
+impl<'a, const N: usize> IntoParameterString<'a> for &'a ArrayString<N> {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(self.units_with_nul(), None)
+    }
+}
+///Borrows the interned string, without copying or converting it.
+impl IntoParameterString<'static> for Atom {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.0.into_parameter_string(pool)
+    }
+}
+///Hands over the string's storage, without copying or converting it.
+impl IntoParameterString<'static> for OwnedString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'static> {
+        ParameterString::from_vec_with_nul(self.0)
+    }
+}
+///Borrows a borrowed string, and hands over the storage of an owned one.
+impl<'a> IntoParameterString<'a> for MaybeOwnedString<'a> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        match self {
+            MaybeOwnedString::Borrowed(string) => string.into_parameter_string(pool),
+            MaybeOwnedString::Owned(string) => string.into_parameter_string(pool),
+        }
+    }
+}
+impl<'a, 'b> IntoParameterString<'b> for &'b MaybeOwnedString<'a> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'b> {
+        (**self).into_parameter_string(pool)
+    }
+}
+///Borrows the text of a parameter string that borrows it, and otherwise takes over its storage where possible.
+impl<'a> From<ParameterString<'a>> for MaybeOwnedString<'a> {
+    fn from(string: ParameterString<'a>) -> Self {
+        match string {
+            ParameterString(units, None) => MaybeOwnedString::Borrowed(NativeStr::from_units_with_nul(units)),
+            string => MaybeOwnedString::Owned(OwnedString::take_parameter_string(string)),
+        }
+    }
+}
+///Shares the string's storage, without copying or converting it.
+impl IntoParameterString<'static> for SharedString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'static> {
+        let shared = self.0;
+        //fool rust into letting us take &temp
+        let slice = unsafe{std::slice::from_raw_parts(shared.0.as_ptr(), shared.0.len())};
+        ParameterString(slice, Some(Storage::Shared(shared)))
+    }
+}
+///Borrows the string, without copying or converting it.
+impl<'a> IntoParameterString<'a> for &'a SharedString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(&self.0.0, None)
+    }
+}
+
+///An instance created by the [pstr!] macro.  This is a static string.
+///
+/// Instances can be created with the [pstr!] macro.
+#[derive(Copy,Clone,PartialEq,Eq)]
+pub struct PStr(pub &'static [u8]);
+impl PStr {
+    fn units(&self) -> &'static [u8] {
+        self.0.split_last().unwrap().1
+    }
+    pub(crate) fn native_str(self) -> &'static NativeStr {
+        NativeStr::from_units_with_nul(self.0)
+    }
+}
+impl IntoParameterString<'static> for PStr {
+    fn into_parameter_string(self,_pool: &ReleasePool) -> ParameterString<'static> {
+        ParameterString(self.0, None)
+    }
+}
+
+impl std::fmt::Display for PStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.units(), f)
+    }
+}
+impl std::fmt::Debug for PStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.units(), f)
+    }
+}
+///Hashes the same as the equivalent [str].
+impl Hash for PStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.units(), state)
+    }
+}
+impl PartialOrd for PStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].  UTF-8 byte order is also scalar value order.
+impl Ord for PStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.units().cmp(other.units())
+    }
+}
+///The empty string.
+impl Default for PStr {
+    fn default() -> Self {
+        PStr(&[0])
+    }
+}
+impl PartialEq<str> for PStr {
+    fn eq(&self, other: &str) -> bool {
+        self.units() == other.as_bytes()
+    }
+}
+impl PartialEq<&str> for PStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.units() == other.as_bytes()
+    }
+}
+
+/**
+An owned string type.  This may be appropriate for long-term string storage in a struct field.
+
+In some cases the implementation may copy the string into the type, in other cases there may
+be some platform-specific trick that can avoid a copy in certain cases.
+
+# Example
+```
+use pcore::string::{OwnedString,IntoParameterString};
+use pcore::release_pool::ReleasePool;
+struct MyType {
+     inner: OwnedString,
+}
+impl MyType {
+    fn new<'a, S: IntoParameterString<'a>>(string: S, pool: &ReleasePool) -> Self {
+        Self { inner: OwnedString::new(string,pool) }
+    }
+}
+```
+ */
+#[derive(Clone,PartialEq,Eq)]
+pub struct OwnedString(Vec<u8>);
+impl OwnedString {
+    pub fn new<'a, S: IntoParameterString<'a>>(string: S, pool: &ReleasePool) -> Self {
+        OwnedString::take_parameter_string(string.into_parameter_string(pool))
+    }
+    ///Takes over the storage of `parameter_string` where possible, otherwise copies it.
+    fn take_parameter_string(parameter_string: ParameterString<'_>) -> Self {
+        let units = match parameter_string {
+            ParameterString(_, Some(Storage::Heap(v))) => {
+                //move the vector into the new type
+                v
+            }
+            ParameterString(_, Some(Storage::Shared(shared))) => {
+                //if this was the last reference, take the vector out of it
+                Arc::try_unwrap(shared).map(|owned| owned.0).unwrap_or_else(|shared| shared.0.clone())
+            }
+            ParameterString(slice,None | Some(Storage::Arena(_))) => {
+                //will require a clone
+                slice.to_owned()
+            }
+        };
+        Self(units)
+    }
+    ///Takes ownership of `v`, which must be null-terminated.  Its spare capacity is kept rather than reallocated away.
+    pub(crate) fn from_vec_with_nul(v: Vec<u8>) -> Self {
+        OwnedString(v)
+    }
+    ///Formats `args` into a new string.  This is what [pformat!](crate::pformat) expands to.
+    ///
+    /// Panics if [Self::try_format] would fail.
+    pub fn format(args: std::fmt::Arguments<'_>) -> Self {
+        OwnedString::try_format(args).unwrap()
+    }
+    ///Formats `args` into a new string, or fails if the result contains a null.
+    pub fn try_format(args: std::fmt::Arguments<'_>) -> Result<Self, Error> {
+        record_conversion();
+        let bytes = match args.as_str() {
+            //a format string without arguments skips the formatting machinery
+            Some(piece) => piece.as_bytes().to_vec(),
+            None => std::fmt::format(args).into_bytes(),
+        };
+        try_push_nul(bytes).map(OwnedString::from_vec_with_nul)
+    }
+    fn units(&self) -> &[u8] {
+        self.0.split_last().unwrap().1
+    }
+}
+impl std::fmt::Display for OwnedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.0.split_last().unwrap().1, f)
+    }
+}
+
+impl<'a> IntoParameterString<'a> for &'a OwnedString {
+    fn into_parameter_string(self,_pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(&self.0, None)
+    }
+}
+impl std::fmt::Debug for OwnedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.units(), f)
+    }
+}
+///Hashes the same as the equivalent [str].
+impl Hash for OwnedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.units(), state)
+    }
+}
+impl PartialOrd for OwnedString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].  UTF-8 byte order is also scalar value order.
+impl Ord for OwnedString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.units().cmp(other.units())
+    }
+}
+///The empty string.
+impl Default for OwnedString {
+    fn default() -> Self {
+        OwnedString(vec![0])
+    }
+}
+impl PartialEq<str> for OwnedString {
+    fn eq(&self, other: &str) -> bool {
+        self.units() == other.as_bytes()
+    }
+}
+impl PartialEq<&str> for OwnedString {
+    fn eq(&self, other: &&str) -> bool {
+        self.units() == other.as_bytes()
+    }
+}
+
+///Assembles an [OwnedString] a piece at a time, in the platform's encoding.
+///
+/// Rust text is encoded as it's appended, and platform strings are appended as-is.
+///
+/// ```
+/// use pcore::string::OwnedStringBuilder;
+/// use std::fmt::Write;
+/// let mut builder = OwnedStringBuilder::new();
+/// builder.push_str("hello");
+/// builder.push(' ');
+/// write!(builder, "{}", 42).unwrap();
+/// assert_eq!(builder.finish(), "hello 42");
+/// ```
+#[derive(Default)]
+pub struct OwnedStringBuilder {
+    //UTF-8, without a null terminator
+    units: Vec<u8>,
+}
+impl OwnedStringBuilder {
+    ///Creates an empty builder.
+    pub fn new() -> Self {
+        OwnedStringBuilder::default()
+    }
+    ///Creates an empty builder with room for `capacity` code units in the platform's encoding.
+    pub fn with_capacity(capacity: usize) -> Self {
+        OwnedStringBuilder { units: Vec::with_capacity(capacity) }
+    }
+    ///Reserves room for at least `additional` more code units in the platform's encoding.
+    pub fn reserve(&mut self, additional: usize) {
+        self.units.reserve(additional)
+    }
+    ///Appends a Rust string.
+    pub fn push_str(&mut self, s: &str) {
+        self.units.extend_from_slice(s.as_bytes());
+    }
+    ///Appends a character.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+    ///Appends a platform string, without re-encoding it.
+    pub fn push_native(&mut self, string: &ParameterString<'_>) {
+        self.units.extend_from_slice(string.units());
+    }
+    ///Finishes the string.
+    ///
+    /// Panics if [Self::try_finish] would fail.
+    pub fn finish(self) -> OwnedString {
+        self.try_finish().unwrap()
+    }
+    ///Finishes the string, or fails if a null was appended.
+    pub fn try_finish(self) -> Result<OwnedString, Error> {
+        let mut units = self.units;
+        if units.contains(&0) {
+            return Err(Error::interior_nul());
+        }
+        units.try_reserve_exact(1).map_err(|_| Error::out_of_memory())?;
+        units.push(0);
+        Ok(OwnedString::from_vec_with_nul(units))
+    }
+}
+impl Write for OwnedStringBuilder {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+    fn write_char(&mut self, c: char) -> std::fmt::Result {
+        self.push(c);
+        Ok(())
+    }
+}
+impl Extend<char> for OwnedStringBuilder {
+    fn extend<I: IntoIterator<Item=char>>(&mut self, iter: I) {
+        for c in iter {
+            self.push(c);
+        }
+    }
+}
+impl<'a> Extend<&'a str> for OwnedStringBuilder {
+    fn extend<I: IntoIterator<Item=&'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.push_str(s);
+        }
+    }
+}
+
+/**
+Type that can be created from a platform string.  This is the reverse of [IntoParameterString].
+
+Binding authors can return any conforming type, and let the caller choose:
+
+```
+use pcore::string::{FromPlatformString, IntoParameterString};
+use pcore::release_pool::ReleasePool;
+use pcore::error::Error;
+fn name<T: FromPlatformString>(pool: &ReleasePool) -> Result<T, Error> {
+    //in a real binding, this would be the result of an OS API
+    let parameter_string = "name".into_parameter_string(pool);
+    T::from_parameter_string(parameter_string, pool)
+}
+let pool = unsafe{ReleasePool::new()};
+let name: String = name(&pool).unwrap();
+assert_eq!(name, "name");
+```
+
+Conversions fail if the string can't be represented in the output type, such as data that isn't UTF-8 in a [String].
+
+Implementing it only requires converting from a borrowed [NativeStr], which is the same on every platform:
+
+```
+use pcore::string::{FromPlatformString, NativeStr};
+use pcore::error::Error;
+struct Shouted(String);
+impl FromPlatformString for Shouted {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        view.try_to_string().map(|s| Shouted(s.to_uppercase()))
+    }
+}
+assert_eq!(Shouted::from_native_str(&pcore::pstr!("hi")).unwrap().0, "HI");
+```
+*/
+pub trait FromPlatformString: Sized {
+    ///Converts from a parameter string, reusing its storage where possible.
+    fn from_parameter_string(string: ParameterString<'_>, _pool: &ReleasePool) -> Result<Self, Error> {
+        Self::from_native_str(&string)
+    }
+    ///Converts from a borrowed platform string.
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error>;
+}
+impl FromPlatformString for String {
+    fn from_parameter_string(string: ParameterString<'_>, _pool: &ReleasePool) -> Result<Self, Error> {
+        String::from_utf8(string.into_vec()).map_err(|_| Error::invalid_encoding())
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        view.try_to_string()
+    }
+}
+impl FromPlatformString for Box<str> {
+    fn from_parameter_string(string: ParameterString<'_>, pool: &ReleasePool) -> Result<Self, Error> {
+        String::from_parameter_string(string, pool).map(String::into_boxed_str)
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        String::from_native_str(view).map(String::into_boxed_str)
+    }
+}
+///This is lossless.
+impl FromPlatformString for OsString {
+    fn from_parameter_string(string: ParameterString<'_>, _pool: &ReleasePool) -> Result<Self, Error> {
+        Ok(OsString::from_vec(string.into_vec()))
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        Ok(view.to_os_string())
+    }
+}
+impl FromPlatformString for PathBuf {
+    fn from_parameter_string(string: ParameterString<'_>, pool: &ReleasePool) -> Result<Self, Error> {
+        OsString::from_parameter_string(string, pool).map(PathBuf::from)
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        OsString::from_native_str(view).map(PathBuf::from)
+    }
+}
+impl FromPlatformString for OwnedString {
+    fn from_parameter_string(string: ParameterString<'_>, pool: &ReleasePool) -> Result<Self, Error> {
+        Ok(OwnedString::new(string, pool))
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        Ok(view.to_owned())
+    }
+}
+
+/// Provides a compile-time optimized path for parameter strings.
+///
+/// This macro is defined to return a [PStr]
+/// ```
+/// use pcore::pstr;
+/// let e = pstr!("test");
+///
+/// ```
+#[macro_export]
+macro_rules! pstr {
+    ($expr:literal) => {
+        {
+            pcore::string::PStr(concat!($expr, "\0").as_bytes())
+        }
+
+    }
+}
+
+#[test] fn str_into() {
+    let pool = unsafe{ReleasePool::new()};
+    let parameter_string = "hello".into_parameter_string(&pool);
+    assert_eq!(parameter_string.c_str_view().to_bytes(), b"hello");
+}
+
+#[test] fn owned_string_reuses_storage() {
+    let pool = unsafe{ReleasePool::new()};
+    //spare capacity is kept, rather than reallocated away
+    let s = String::with_capacity(64) + "hello";
+    let ptr = s.as_ptr();
+    let capacity = s.capacity();
+    let owned = OwnedString::new(s, &pool);
+    assert_eq!(owned.0.as_ptr(), ptr);
+    assert_eq!(owned.0.capacity(), capacity);
+    assert_eq!(owned.to_string(), "hello");
+}
+
+#[test] fn arena_conversion() {
+    use crate::release_pool::autoreleasepool_with_arena;
+    autoreleasepool_with_arena(1024, |pool| {
+        let parameter_string = std::path::Path::new("/tmp").into_parameter_string(pool);
+        assert!(matches!(parameter_string.1, Some(Storage::Arena(_))));
+        assert_eq!(OwnedString::new(parameter_string, pool).to_string(), "/tmp");
+        assert_eq!(pool.arena_used(), Some(1024));
+    })
+}
+
+#[test] fn to_string() {
+    let p = pstr!("Hello world");
+    assert_eq!(p.to_string(), "Hello world");
+}
+
+#[test] fn read_back() {
+    use std::os::unix::ffi::OsStringExt;
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new("héllo 😀", &pool);
+    assert_eq!(owned.try_to_string().unwrap(), "héllo 😀");
+    assert_eq!(owned.len_utf8(), "héllo 😀".len());
+    assert_eq!(owned.len_utf16(), 8);
+    assert!(!owned.is_empty());
+    assert_eq!(owned.chars().count(), 7);
+
+    let invalid = OsString::from_vec(vec![b'h', 0xFF, b'i']).into_parameter_string(&pool);
+    assert!(invalid.try_to_string().is_err());
+    assert_eq!(invalid.to_string_lossy(), "h\u{FFFD}i");
+    assert_eq!(invalid.chars().collect::<String>(), "h\u{FFFD}i");
+    assert_eq!(invalid.len_utf8(), 5);
+    assert_eq!(invalid.len_utf16(), 3);
+    assert!("".into_parameter_string(&pool).is_empty());
+}
+
+#[test] fn invalid_bytes() {
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new(OsString::from_vec(vec![b'h', 0xFF, b'i']), &pool);
+    assert_eq!(owned.to_string(), "h\u{FFFD}i");
+    assert_eq!(format!("{:?}", owned), "h\\xffi");
+    assert_eq!(owned.to_os_string().into_vec(), [b'h', 0xFF, b'i']);
+}
+
+#[test] fn interior_nul() {
+    let pool = unsafe{ReleasePool::new()};
+    let error = "a\0b".try_into_parameter_string(&pool).unwrap_err();
+    assert_eq!(error.into_errno(), libc::EINVAL);
+    assert!(String::from("a\0b").try_into_parameter_string(&pool).is_err());
+    assert!(std::path::Path::new("a\0b").try_into_parameter_string(&pool).is_err());
+    assert_eq!("ab".try_into_parameter_string(&pool).unwrap().c_str_view().to_bytes(), b"ab");
+    assert!(pstr!("ab").try_into_parameter_string(&pool).is_ok());
+}
+
+#[test] fn traits() {
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new("hello", &pool);
+    assert_eq!(owned.clone(), owned);
+    assert_eq!(owned, "hello");
+    assert_eq!(owned, *"hello");
+    assert_ne!(owned, "hell");
+    assert!(OwnedString::new("a", &pool) < OwnedString::new("ab", &pool));
+    assert!(OwnedString::default().is_empty());
+    assert_eq!(OwnedString::default(), "");
+    assert_eq!(pstr!("hello"), "hello");
+    assert_eq!(format!("{:?}", pstr!("hello")), "hello");
+    assert_eq!(PStr::default(), "");
+}
+
+#[test] fn consistent_with_str() {
+    use std::collections::hash_map::DefaultHasher;
+    fn hash<T: Hash + ?Sized>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+    let pool = unsafe{ReleasePool::new()};
+    //U+FF61 sorts after U+1F600 by UTF-16 code unit, but before it by scalar value
+    let strs = ["", "a", "ab", "b", "héllo", "\u{FF61}", "😀", &"x".repeat(300)];
+    for a in strs {
+        let owned = OwnedString::new(a, &pool);
+        assert_eq!(hash(&owned), hash(a));
+        assert_eq!(hash(&a.into_parameter_string(&pool)), hash(a));
+        for b in strs {
+            assert_eq!(owned.cmp(&OwnedString::new(b, &pool)), a.cmp(b), "{:?} {:?}", a, b);
+        }
+    }
+    assert_eq!(hash(&pstr!("héllo")), hash("héllo"));
+    assert!(pstr!("\u{FF61}") < pstr!("😀"));
+}
+
+#[test] fn std_types() {
+    let pool = unsafe{ReleasePool::new()};
+    fn check<'a, S: IntoParameterString<'a>>(s: S, pool: &ReleasePool) {
+        assert_eq!(s.into_parameter_string(pool).c_str_view().to_bytes(), b"hi");
+    }
+    let string = String::from("hi");
+    check(&string, &pool);
+    check(Box::<str>::from("hi"), &pool);
+    check(Rc::<str>::from("hi"), &pool);
+    check(Arc::<str>::from("hi"), &pool);
+    check(Cow::Borrowed("hi"), &pool);
+    check(Cow::<str>::Owned("hi".to_owned()), &pool);
+    check(OsStr::new("hi"), &pool);
+    check(OsString::from("hi"), &pool);
+    check(PathBuf::from("hi"), &pool);
+    check(Path::new("hi"), &pool);
+    check(CString::new("hi").unwrap(), &pool);
+    let c_str = CString::new("hi").unwrap();
+    let parameter_string = c_str.as_c_str().into_parameter_string(&pool);
+    //borrowed without copying
+    assert_eq!(parameter_string.0.as_ptr(), c_str.as_ptr() as *const u8);
+    assert_eq!(parameter_string.c_str_view(), c_str.as_c_str());
+    assert_eq!('é'.into_parameter_string(&pool).c_str_view().to_bytes(), "é".as_bytes());
+    assert!('\0'.try_into_parameter_string(&pool).is_err());
+}
+
+#[test] fn from_platform_string() {
+    let pool = unsafe{ReleasePool::new()};
+    fn check<T: FromPlatformString>(pool: &ReleasePool) -> T {
+        T::from_parameter_string("hi".into_parameter_string(pool), pool).unwrap()
+    }
+    assert_eq!(check::<String>(&pool), "hi");
+    assert_eq!(&*check::<Box<str>>(&pool), "hi");
+    assert_eq!(check::<OsString>(&pool), "hi");
+    assert_eq!(check::<PathBuf>(&pool), Path::new("hi"));
+    assert_eq!(check::<OwnedString>(&pool), "hi");
+    //reuses the storage of an owned parameter string
+    let parameter_string = String::from("hi").into_parameter_string(&pool);
+    let ptr = parameter_string.0.as_ptr();
+    let string = String::from_parameter_string(parameter_string, &pool).unwrap();
+    assert_eq!(string.as_ptr(), ptr);
+    let invalid = CStr::from_bytes_with_nul(&[104, 0xFF, 0]).unwrap().into_parameter_string(&pool);
+    assert!(String::from_native_str(&invalid).is_err());
+    assert_eq!(OsString::from_native_str(&invalid).unwrap().into_vec(), [104, 0xFF]);
+}
+
+#[test] fn pformat() {
+    let pool = unsafe{ReleasePool::new()};
+    let name = "wörld";
+    assert_eq!(crate::pformat!("hello {}", name), "hello wörld");
+    assert_eq!(crate::pformat!("{{static}}"), "{static}");
+    assert_eq!(format_args!("{}-{}", 1, 2).into_parameter_string(&pool).to_string_lossy(), "1-2");
+    assert_eq!(format_args!("piece").into_parameter_string(&pool).to_string_lossy(), "piece");
+    assert!(OwnedString::try_format(format_args!("a{}b", '\0')).is_err());
+    assert!(OwnedString::try_format(format_args!("a\0b")).is_err());
+    assert!(format_args!("a{}b", '\0').try_into_parameter_string(&pool).is_err());
+}
+
+#[test] fn pformat_records_conversion() {
+    crate::release_pool::autoreleasepool(|pool| {
+        let before = pool.innermost_statistics().conversions;
+        let _ = crate::pformat!("hello {}", 1);
+        assert_eq!(pool.innermost_statistics().conversions, before + 1);
+        let _ = format_args!("piece").into_parameter_string(pool);
+        assert_eq!(pool.innermost_statistics().conversions, before + 2);
+    })
+}
+
+#[test] fn builder() {
+    let pool = unsafe{ReleasePool::new()};
+    let mut builder = OwnedStringBuilder::with_capacity(4);
+    builder.push_str("héllo");
+    builder.push(' ');
+    builder.push('😀');
+    builder.reserve(16);
+    builder.push_native(&pstr!(" wörld").into_parameter_string(&pool));
+    builder.extend(['!', '?']);
+    builder.extend([" a", "b"]);
+    write!(builder, " {}", 42).unwrap();
+    let owned = builder.finish();
+    assert_eq!(owned, "héllo 😀 wörld!? ab 42");
+    assert_eq!(OwnedStringBuilder::new().finish(), "");
+    let mut builder = OwnedStringBuilder::new();
+    builder.push('\0');
+    assert!(builder.try_finish().is_err());
+}
+
+#[test] fn native_str() {
+    use std::collections::HashMap;
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new("héllo", &pool);
+    let native: &NativeStr = &owned;
+    assert_eq!(native, "héllo");
+    assert_eq!(native.len_utf8(), 6);
+    assert_eq!(native.to_string(), "héllo");
+    assert_eq!(&*"héllo".into_parameter_string(&pool), native);
+    assert_eq!(&*pstr!("hi"), "hi");
+    assert_eq!(native.to_owned(), owned);
+    assert_eq!(OwnedString::new(native, &pool), owned);
+    let mut map = HashMap::new();
+    map.insert(owned.clone(), 1);
+    assert_eq!(map.get(native), Some(&1));
+    fn len<S: AsRef<NativeStr>>(string: S) -> usize {
+        string.as_ref().len_utf16()
+    }
+    assert_eq!(len(&owned), 5);
+    assert_eq!(len(pstr!("hi")), 2);
+    assert_eq!(native.c_str_view().to_bytes(), "héllo".as_bytes());
+}
+
+#[test] fn erased_length() {
+    let pool = unsafe{ReleasePool::new()};
+    let storage = b"hi\0j";
+    let erased = unsafe{U8ZErasedLength::with_u8_z_unknown_length(storage)};
+    assert_eq!(erased.find_length().to_bytes_with_nul(), b"hi\0");
+    assert_eq!(format!("{:?}", erased), "hi");
+    assert_eq!(&*erased.into_parameter_string(&pool), "hi");
+    assert_eq!(erased.find_length_within(3).unwrap().to_bytes(), b"hi");
+    assert!(erased.find_length_within(2).is_none());
+    assert!(erased.find_length_within(100).is_some());
+    let unterminated = U8ZErasedLength::with_u8_unknown_length(b"hi");
+    assert!(unterminated.find_length_within(usize::MAX).is_none());
+    assert_eq!(format!("{:?}", unterminated), "hi");
+
+    let pointed = unsafe{U8ZErasedLength::from_ptr(storage.as_ptr() as *const c_char, storage.len())};
+    assert_eq!(pointed.find_length().to_bytes(), b"hi");
+    assert_eq!(unsafe{CStr::from_ptr(pointed.into_unsafe_const_cstr())}.to_bytes(), b"hi");
+    //only the first two bytes may be read
+    let bounded = unsafe{U8ZErasedLength::from_ptr(storage.as_ptr() as *const c_char, 2)};
+    assert!(bounded.find_length_within(usize::MAX).is_none());
+}
+
+//under Miri, this checks that converted storage outlives the pointer
+#[test] fn unsafe_const_cstr() {
+    let pool = unsafe{ReleasePool::new()};
+    let converted = unsafe{String::from("hello").into_unsafe_const_cstr()};
+    let borrowed = unsafe{pstr!("hi").into_unsafe_const_cstr()};
+    //would likely reuse the conversion's allocation, had it been freed
+    let reuse = vec![b'x'; 6];
+    assert_eq!(unsafe{CStr::from_ptr(converted)}.to_bytes(), b"hello");
+    assert_eq!(unsafe{CStr::from_ptr(borrowed)}.to_bytes(), b"hi");
+    assert_eq!(pool.innermost_deferred(), 1);
+    drop(reuse);
+}
@@ -1,4 +1,18 @@
 use objr::bindings::*;
+use std::borrow::{Borrow, Cow};
+use std::ops::Deref;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use super::utf16;
+use super::transcode;
+use std::fmt::{Formatter, Write};
+use crate::error::Error;
+use crate::release_pool::stack::record_conversion;
+use super::{ArrayString, Atom, MaybeOwnedString, SharedString};
 use std::os::raw::c_ulong;
 pub use objr::foundation::objc_nsstring as __objc_nsstring;
 
@@ -48,6 +62,14 @@ pub trait IntoParameterString<'a> {
     fn into_parameter_string(self, pool: &ActiveAutoreleasePool) -> ParameterString<'a> where Self: Sized {
         ParameterString(self.into_nsstring(pool))
     }
+    ///Erases to a parameter string, or fails if the string can't be converted.
+    ///
+    /// NSString can hold interior nulls, so on macOS this only fails for data that isn't valid unicode, such as an [OsStr]
+    /// that isn't UTF-8.  [into_nsstring](Self::into_nsstring) and [into_parameter_string](Self::into_parameter_string) panic
+    /// where this would fail.
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'a>, Error> where Self: Sized {
+        Ok(self.into_parameter_string(pool))
+    }
 }
 
 //private extensions to NSString
@@ -56,13 +78,31 @@ objc_selector_group! {
     trait NSStringExtensionSelectors {
         @selector("initWithBytesNoCopy:length:encoding:freeWhenDone:")
         @selector("initWithBytesNoCopy:length:encoding:deallocator:")
+        @selector("length")
+        @selector("getCharacters:range:")
+        @selector("initWithCapacity:")
+        @selector("appendString:")
     }
     impl NSStringExtensionSelectors for Sel {}
 }
 
+objc_class! {
+    struct NSMutableString {
+        @class(NSMutableString)
+    }
+}
+
 trait NSStringExtension {
     fn from_bytes_no_copy<'a>(bytes: &'a [u8], pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString>;
     fn from_bytes_no_copy_deallocator<'a>(bytes: &'a [u8], deallocator: &Deallocator, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString>;
+    ///The length of the string in UTF-16 code units.
+    fn utf16_len(&self) -> usize;
+    ///Copies the string's UTF-16 code units.
+    fn utf16_units(&self) -> Vec<u16>;
+    ///Copies `buffer.len()` UTF-16 code units starting at `location` into `buffer`.
+    fn get_utf16_units(&self, buffer: &mut [u16], location: usize);
+    ///Iterates over the string's UTF-16 code units without allocating.
+    fn utf16_iter(&self) -> Utf16Units<'_>;
 }
 
 blocksr::once_escaping!(Deallocator(ptr: *const core::ffi::c_void, len: NSUInteger) -> ());
@@ -83,17 +123,161 @@ impl NSStringExtension for NSString {
             NSString::assume_nonnil(ptr).assume_retained_limited()
         }
     }
+    fn utf16_len(&self) -> usize {
+        unsafe {
+            //this doesn't allocate
+            let pool = ActiveAutoreleasePool::assume_autoreleasepool();
+            let len: NSUInteger = Self::perform_primitive(self.assume_nonmut_perform(), Sel::length(), &pool, ());
+            len as usize
+        }
+    }
+    fn utf16_units(&self) -> Vec<u16> {
+        let mut units = vec![0; self.utf16_len()];
+        self.get_utf16_units(&mut units, 0);
+        units
+    }
+    fn get_utf16_units(&self, buffer: &mut [u16], location: usize) {
+        unsafe {
+            //this doesn't allocate
+            let pool = ActiveAutoreleasePool::assume_autoreleasepool();
+            //NSRange is two NSUIntegers, which are passed the same way as two separate arguments
+            let _: () = Self::perform_primitive(self.assume_nonmut_perform(), Sel::getCharacters_range(), &pool, (buffer.as_mut_ptr(), location as NSUInteger, buffer.len() as NSUInteger));
+        }
+    }
+    fn utf16_iter(&self) -> Utf16Units<'_> {
+        Utf16Units { string: self, len: self.utf16_len(), location: 0, buffer: [0; 32], buffer_position: 0, buffer_len: 0 }
+    }
+}
+
+///Iterates over an NSString's UTF-16 code units, copying them out a chunk at a time.
+struct Utf16Units<'a> {
+    string: &'a NSString,
+    len: usize,
+    location: usize,
+    buffer: [u16; 32],
+    buffer_position: usize,
+    buffer_len: usize,
+}
+impl Iterator for Utf16Units<'_> {
+    type Item = u16;
+    fn next(&mut self) -> Option<u16> {
+        if self.buffer_position == self.buffer_len {
+            if self.location == self.len {
+                return None;
+            }
+            let chunk = (self.len - self.location).min(self.buffer.len());
+            self.string.get_utf16_units(&mut self.buffer[..chunk], self.location);
+            self.location += chunk;
+            self.buffer_position = 0;
+            self.buffer_len = chunk;
+        }
+        let unit = self.buffer[self.buffer_position];
+        self.buffer_position += 1;
+        Some(unit)
+    }
+}
+
+///Calls `f` with the string's UTF-16 code units, copied onto the stack if they fit.
+fn with_utf16_units<R, F: FnOnce(&[u16]) -> R>(string: &NSString, f: F) -> R {
+    let mut stack = [0; 128];
+    let len = string.utf16_len();
+    if len <= stack.len() {
+        string.get_utf16_units(&mut stack[..len], 0);
+        f(&stack[..len])
+    }
+    else {
+        f(&string.utf16_units())
+    }
+}
+
+//read-back implementations shared by the string types
+fn chars(string: &NSString) -> impl Iterator<Item=char> + '_ {
+    char::decode_utf16(string.utf16_iter()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+fn to_string_lossy(string: &NSString) -> Cow<'_, str> {
+    Cow::Owned(transcode::decode_utf16_lossy(&string.utf16_units()))
+}
+fn try_to_string(string: &NSString) -> Result<String, Error> {
+    transcode::try_decode_utf16(&string.utf16_units())
+}
+fn len_utf8(string: &NSString) -> usize {
+    with_utf16_units(string, transcode::utf8_len_lossy)
+}
+fn len_utf16(string: &NSString) -> usize {
+    //an unpaired surrogate and U+FFFD are both one unit
+    string.utf16_len()
+}
+fn to_os_string(string: &NSString) -> OsString {
+    OsString::from(transcode::decode_utf16_lossy(&string.utf16_units()))
+}
+///Compares with a Rust string, without converting the string.
+fn eq_str(string: &NSString, other: &str) -> bool {
+    with_utf16_units(string, |units| transcode::eq_utf16(units, other))
+}
+fn cmp(string: &NSString, other: &NSString) -> Ordering {
+    utf16::cmp(string.utf16_iter(), other.utf16_iter())
+}
+fn hash<H: Hasher>(string: &NSString, state: &mut H) {
+    with_utf16_units(string, |units| utf16::hash(units, state))
+}
+///Writes the string, replacing invalid data with U+FFFD.
+fn fmt_lossy(string: &NSString, f: &mut Formatter<'_>) -> std::fmt::Result {
+    for c in chars(string) {
+        f.write_char(c)?;
+    }
+    Ok(())
+}
+///Writes the string, escaping unpaired surrogates as `\\u{d800}`.
+fn fmt_escaped(string: &NSString, f: &mut Formatter<'_>) -> std::fmt::Result {
+    for c in char::decode_utf16(string.utf16_iter()) {
+        match c {
+            Ok(c) => f.write_char(c)?,
+            Err(e) => write!(f, "\\u{{{:x}}}", e.unpaired_surrogate())?,
+        }
+    }
+    Ok(())
 }
 
 impl<'a> IntoParameterString<'a> for &'a str {
     ///Borrow the bytes into an NSString instance.
     fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        record_conversion();
         NSString::from_bytes_no_copy(self.as_bytes(), pool)
     }
 }
 impl IntoParameterString<'static> for String {
     fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
-        //I think this is pinned for the lifetime of the string
+        record_conversion();
+        nsstring_from_string(self, pool)
+    }
+}
+///Creates an NSString that takes over the string's bytes.
+fn nsstring_from_string(string: String, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+    //I think this is pinned for the lifetime of the string
+    let bytes = unsafe{std::slice::from_raw_parts(string.as_ptr(), string.len())};
+    let block = unsafe{Deallocator::new(|_,_| {
+        std::mem::drop(string);
+    })};
+    NSString::from_bytes_no_copy_deallocator(bytes, &block, pool)
+}
+///Formats into UTF-8 that the NSString takes over.  A format string without arguments is used in place, like [pstr!].
+impl<'a> IntoParameterString<'a> for std::fmt::Arguments<'a> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        record_conversion();
+        nsstring_from_arguments(self, pool)
+    }
+}
+fn nsstring_from_arguments<'a>(args: std::fmt::Arguments<'a>, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+    match args.as_str() {
+        Some(piece) => NSString::from_bytes_no_copy(piece.as_bytes(), pool),
+        None => nsstring_from_string(std::fmt::format(args), pool),
+    }
+}
+///Borrows the bytes, keeping the `Arc` alive until the NSString is deallocated.
+impl IntoParameterString<'static> for Arc<str> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        record_conversion();
+        //the allocation is pinned while we hold a reference
         let bytes = unsafe{std::slice::from_raw_parts(self.as_ptr(), self.len())};
         let block = unsafe{Deallocator::new(|_,_| {
             std::mem::drop(self);
@@ -101,6 +285,87 @@ impl IntoParameterString<'static> for String {
         NSString::from_bytes_no_copy_deallocator(bytes, &block, pool)
     }
 }
+///Copies, since an `Rc` can't be released from another thread.
+impl IntoParameterString<'static> for Rc<str> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        String::from(&*self).into_nsstring(pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a String {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        self.as_str().into_nsstring(pool)
+    }
+}
+impl IntoParameterString<'static> for Box<str> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        String::from(self).into_nsstring(pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for Cow<'a, str> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        match self {
+            Cow::Borrowed(s) => s.into_nsstring(pool),
+            Cow::Owned(s) => s.into_nsstring(pool),
+        }
+    }
+}
+impl IntoParameterString<'static> for char {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        String::from(self).into_nsstring(pool)
+    }
+}
+
+//NSString requires valid unicode, so these fail for data that isn't UTF-8
+impl<'a> IntoParameterString<'a> for &'a OsStr {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        self.to_str().ok_or_else(Error::invalid_encoding).unwrap().into_nsstring(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'a>, Error> {
+        Ok(self.to_str().ok_or_else(Error::invalid_encoding)?.into_parameter_string(pool))
+    }
+}
+impl IntoParameterString<'static> for OsString {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        self.into_string().map_err(|_| Error::invalid_encoding()).unwrap().into_nsstring(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'static>, Error> {
+        Ok(self.into_string().map_err(|_| Error::invalid_encoding())?.into_parameter_string(pool))
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a Path {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        self.as_os_str().into_nsstring(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'a>, Error> {
+        self.as_os_str().try_into_parameter_string(pool)
+    }
+}
+impl IntoParameterString<'static> for PathBuf {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        self.into_os_string().into_nsstring(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'static>, Error> {
+        self.into_os_string().try_into_parameter_string(pool)
+    }
+}
+///C strings are treated as UTF-8.
+impl<'a> IntoParameterString<'a> for &'a CStr {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        self.to_str().map_err(|_| Error::invalid_encoding()).unwrap().into_nsstring(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'a>, Error> {
+        Ok(self.to_str().map_err(|_| Error::invalid_encoding())?.into_parameter_string(pool))
+    }
+}
+///C strings are treated as UTF-8.  This reuses the string's allocation.
+impl IntoParameterString<'static> for CString {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        self.into_string().map_err(|_| Error::invalid_encoding()).unwrap().into_nsstring(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ActiveAutoreleasePool) -> Result<ParameterString<'static>, Error> {
+        Ok(self.into_string().map_err(|_| Error::invalid_encoding())?.into_parameter_string(pool))
+    }
+}
 
 /**
 A type that erases [IntoParameterString] into a concrete type with a named lifetime.
@@ -122,13 +387,24 @@ impl<'a> StringBuilder<'a> {
 }
 ```
 */
-#[derive(PartialEq,Eq,Hash)]
+#[derive(PartialEq,Eq)]
 pub struct ParameterString<'a>(StrongLifetimeCell<'a, NSString>);
+///Hashes the same as the equivalent [str].
+impl<'a> Hash for ParameterString<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.nsstring(), state)
+    }
+}
 impl<'a> IntoParameterString<'a> for ParameterString<'a> {
     fn into_nsstring(self, _pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
         self.0
     }
 }
+impl<'a> ParameterString<'a> {
+    fn nsstring(&self) -> &NSString {
+        &self.0
+    }
+}
 /**
 An owned string type.  This may be appropriate for long-term string storage in a struct field.
 
@@ -149,6 +425,7 @@ impl MyType {
 }
 ```
 */
+#[derive(Clone,PartialEq,Eq)]
 pub struct OwnedString(StrongCell<NSString>);
 impl OwnedString {
     ///Create a new [OwnedString] by copying another string.
@@ -156,20 +433,520 @@ impl OwnedString {
         let str = string.into_nsstring(pool);
         OwnedString(str.copy(pool))
     }
+    ///Takes ownership of `string` without copying its bytes.
+    pub(crate) fn from_string(string: String) -> Self {
+        autoreleasepool(|pool| OwnedString::new(string, pool))
+    }
+    ///Formats `args` into a new string.  This is what [pformat!](crate::pformat) expands to.
+    pub fn format(args: std::fmt::Arguments<'_>) -> Self {
+        //recorded against the caller's pool, rather than the temporary one
+        record_conversion();
+        autoreleasepool(|pool| OwnedString(nsstring_from_arguments(args, pool).copy(pool)))
+    }
+    ///Formats `args` into a new string.  This never fails on macOS.
+    pub fn try_format(args: std::fmt::Arguments<'_>) -> Result<Self, Error> {
+        Ok(OwnedString::format(args))
+    }
+    fn nsstring(&self) -> &NSString {
+        &self.0
+    }
+}
+///Hashes the same as the equivalent [str].
+impl Hash for OwnedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.nsstring(), state)
+    }
+}
+impl PartialOrd for OwnedString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for OwnedString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp(self.nsstring(), other.nsstring())
+    }
+}
+impl std::fmt::Display for OwnedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.nsstring(), f)
+    }
+}
+impl std::fmt::Debug for OwnedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.nsstring(), f)
+    }
+}
+///The empty string.
+impl Default for OwnedString {
+    fn default() -> Self {
+        //copying a constant string doesn't autorelease anything
+        let pool = unsafe{ActiveAutoreleasePool::assume_autoreleasepool()};
+        OwnedString::new(PStr::default(), &pool)
+    }
+}
+impl PartialEq<str> for OwnedString {
+    fn eq(&self, other: &str) -> bool {
+        eq_str(self.nsstring(), other)
+    }
+}
+impl PartialEq<&str> for OwnedString {
+    fn eq(&self, other: &&str) -> bool {
+        eq_str(self.nsstring(), other)
+    }
+}
+/**
+A borrowed platform string, like [str] or [OsStr] for the platform's encoding.
+
+[OwnedString], [PStr] and [ParameterString] all dereference to it, so functions that only read a string can take
+a `&NativeStr` without consuming or converting anything.
+
+```
+use pcore::string::{NativeStr, OwnedString};
+use pcore::release_pool::autoreleasepool;
+fn shout(string: &NativeStr) -> String {
+    string.to_string_lossy().to_uppercase()
+}
+autoreleasepool(|pool| {
+    assert_eq!(shout(&OwnedString::new("hi", pool)), "HI");
+    assert_eq!(shout(&pcore::pstr!("hi")), "HI");
+})
+```
+*/
+#[repr(transparent)]
+pub struct NativeStr(NSString);
+impl NativeStr {
+    fn from_nsstring(string: &NSString) -> &NativeStr {
+        //NativeStr is a transparent wrapper around NSString
+        unsafe{&*(string as *const NSString as *const NativeStr)}
+    }
+    fn nsstring(&self) -> &NSString {
+        &self.0
+    }
+    ///Converts to a Rust string, replacing invalid data with U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        to_string_lossy(self.nsstring())
+    }
+    ///Converts to a Rust string, or fails if the string contains invalid data.
+    pub fn try_to_string(&self) -> Result<String, Error> {
+        try_to_string(self.nsstring())
+    }
+    ///The length of the string in UTF-8 code units (bytes).  Invalid data counts as U+FFFD.
+    pub fn len_utf8(&self) -> usize {
+        len_utf8(self.nsstring())
+    }
+    ///The length of the string in UTF-16 code units.  Invalid data counts as U+FFFD.
+    pub fn len_utf16(&self) -> usize {
+        len_utf16(self.nsstring())
+    }
+    ///Whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        len_utf16(self.nsstring()) == 0
+    }
+    ///Iterates over the characters of the string, replacing invalid data with U+FFFD.
+    pub fn chars(&self) -> impl Iterator<Item=char> + '_ {
+        chars(self.nsstring())
+    }
+    ///Converts to an [OsString].  macOS paths can't contain unpaired surrogates, so they're replaced with U+FFFD.
+    pub fn to_os_string(&self) -> OsString {
+        to_os_string(self.nsstring())
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a NativeStr {
+    fn into_nsstring(self, _pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        StrongLifetimeCell::retaining(self.nsstring())
+    }
+}
+impl ToOwned for NativeStr {
+    type Owned = OwnedString;
+    fn to_owned(&self) -> OwnedString {
+        autoreleasepool(|pool| OwnedString(self.nsstring().copy(pool)))
+    }
+}
+impl AsRef<NativeStr> for NativeStr {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl std::fmt::Display for NativeStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.nsstring(), f)
+    }
+}
+impl std::fmt::Debug for NativeStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.nsstring(), f)
+    }
+}
+impl PartialEq for NativeStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.nsstring() == other.nsstring()
+    }
+}
+impl Eq for NativeStr {}
+///Hashes the same as the equivalent [str].
+impl Hash for NativeStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.nsstring(), state)
+    }
+}
+impl PartialOrd for NativeStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
+///Sorts the same as the equivalent [str].
+impl Ord for NativeStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp(self.nsstring(), other.nsstring())
+    }
+}
+impl PartialEq<str> for NativeStr {
+    fn eq(&self, other: &str) -> bool {
+        eq_str(self.nsstring(), other)
+    }
+}
+impl PartialEq<&str> for NativeStr {
+    fn eq(&self, other: &&str) -> bool {
+        eq_str(self.nsstring(), other)
+    }
+}
+impl<'a> Deref for ParameterString<'a> {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_nsstring(&self.0)
+    }
+}
+impl<'a> AsRef<NativeStr> for ParameterString<'a> {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Deref for PStr {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_nsstring(self.0)
+    }
+}
+impl AsRef<NativeStr> for PStr {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Deref for OwnedString {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_nsstring(&self.0)
+    }
+}
+impl AsRef<NativeStr> for OwnedString {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Borrow<NativeStr> for OwnedString {
+    fn borrow(&self) -> &NativeStr {
+        self
+    }
+}
+
+///Borrows the string's inline storage, without copying or converting it.
+impl<'a, const N: usize> IntoParameterString<'a> for &'a ArrayString<N> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        NSString::from_bytes_no_copy(self.units(), pool)
+    }
+}
+///Borrows the interned string, without copying or converting it.
+impl IntoParameterString<'static> for Atom {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        self.0.into_nsstring(pool)
+    }
+}
+///Hands over a reference to the string, without copying or converting it.
+impl IntoParameterString<'static> for OwnedString {
+    fn into_nsstring(self, _pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        let string: *const NSString = self.nsstring();
+        //the new cell takes over our retain
+        std::mem::forget(self);
+        unsafe{StrongLifetimeCell::assume_retained_limited(&*string)}
+    }
+}
+///Borrows a borrowed string, and hands over an owned one.
+impl<'a> IntoParameterString<'a> for MaybeOwnedString<'a> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        match self {
+            MaybeOwnedString::Borrowed(string) => string.into_nsstring(pool),
+            MaybeOwnedString::Owned(string) => string.into_nsstring(pool),
+        }
+    }
+}
+impl<'a, 'b> IntoParameterString<'b> for &'b MaybeOwnedString<'a> {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'b, NSString> {
+        (**self).into_nsstring(pool)
+    }
+}
+///Retains the string, since a parameter string can't lend out its `NSString` for its whole lifetime.
+impl<'a> From<ParameterString<'a>> for MaybeOwnedString<'a> {
+    fn from(string: ParameterString<'a>) -> Self {
+        MaybeOwnedString::Owned(autoreleasepool(|pool| OwnedString::new(string, pool)))
+    }
+}
+///Hands over a reference to the string, without copying or converting it.
+impl IntoParameterString<'static> for SharedString {
+    fn into_nsstring(self, pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
+        self.0.into_nsstring(pool)
+    }
+}
+///Retains the string, without copying or converting it.
+impl<'a> IntoParameterString<'a> for &'a SharedString {
+    fn into_nsstring(self, _pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'a, NSString> {
+        StrongLifetimeCell::retaining(self.0.nsstring())
+    }
+}
+
 ///An instance created by the [pstr!] macro.  This is a static string.
 ///
 /// Instances can be created with the [pstr!] macro.
-#[derive(Copy,Clone,Debug)]
+#[derive(Copy,Clone,PartialEq,Eq)]
 pub struct PStr(
     #[doc(hidden)]
     pub &'static NSString
 );
+impl PStr {
+    pub(crate) fn native_str(self) -> &'static NativeStr {
+        NativeStr::from_nsstring(self.0)
+    }
+}
 impl IntoParameterString<'static> for PStr {
     fn into_nsstring(self, _pool: &ActiveAutoreleasePool) -> StrongLifetimeCell<'static, NSString> {
         unsafe{StrongLifetimeCell::assume_retained_limited(self.0) }
     }
 }
+///Hashes the same as the equivalent [str].
+impl Hash for PStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash(self.0, state)
+    }
+}
+impl PartialOrd for PStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for PStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp(self.0, other.0)
+    }
+}
+impl std::fmt::Display for PStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.0, f)
+    }
+}
+impl std::fmt::Debug for PStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.0, f)
+    }
+}
+///The empty string.
+impl Default for PStr {
+    fn default() -> Self {
+        crate::pstr!("")
+    }
+}
+impl PartialEq<str> for PStr {
+    fn eq(&self, other: &str) -> bool {
+        eq_str(self.0, other)
+    }
+}
+impl PartialEq<&str> for PStr {
+    fn eq(&self, other: &&str) -> bool {
+        eq_str(self.0, other)
+    }
+}
+
+///Assembles an [OwnedString] a piece at a time, in the platform's encoding.
+///
+/// Text is appended to an `NSMutableString`, which becomes the finished string without copying.
+///
+/// ```
+/// use pcore::string::OwnedStringBuilder;
+/// use std::fmt::Write;
+/// let mut builder = OwnedStringBuilder::new();
+/// builder.push_str("hello");
+/// builder.push(' ');
+/// write!(builder, "{}", 42).unwrap();
+/// assert_eq!(builder.finish(), "hello 42");
+/// ```
+pub struct OwnedStringBuilder {
+    string: StrongCell<NSMutableString>,
+    //the capacity the string was created with, in UTF-16 code units
+    capacity: usize,
+}
+impl OwnedStringBuilder {
+    ///Creates an empty builder.
+    pub fn new() -> Self {
+        OwnedStringBuilder::with_capacity(0)
+    }
+    ///Creates an empty builder with room for `capacity` code units in the platform's encoding.
+    pub fn with_capacity(capacity: usize) -> Self {
+        autoreleasepool(|pool| unsafe {
+            let uninit = NSMutableString::class().alloc(pool);
+            let ptr = NSMutableString::perform(uninit, Sel::initWithCapacity_(), pool, (capacity as NSUInteger,));
+            OwnedStringBuilder { string: NSMutableString::assume_nonnil(ptr).assume_retained(), capacity }
+        })
+    }
+    ///Reserves room for at least `additional` more code units in the platform's encoding.
+    ///
+    /// `NSMutableString` can only reserve room when it's created, so if there isn't room, the contents are moved
+    /// into a new string that has it.
+    pub fn reserve(&mut self, additional: usize) {
+        let len = self.string().utf16_len();
+        let needed = len.saturating_add(additional);
+        if needed <= self.capacity {
+            return
+        }
+        //like `Vec`, grow at least geometrically so that repeated reserves are amortized
+        let mut grown = OwnedStringBuilder::with_capacity(needed.max(self.capacity.saturating_mul(2)));
+        if len > 0 {
+            autoreleasepool(|pool| grown.append(self.string(), pool));
+        }
+        *self = grown;
+    }
+    fn string(&self) -> &NSString {
+        //NSMutableString is a subclass of NSString
+        unsafe{ &*(&*self.string as *const NSMutableString as *const NSString) }
+    }
+    ///Appends a Rust string.
+    pub fn push_str(&mut self, s: &str) {
+        autoreleasepool(|pool| {
+            self.append(&NSString::from_bytes_no_copy(s.as_bytes(), pool), pool);
+        })
+    }
+    ///Appends a character.
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+    ///Appends a platform string, without re-encoding it.
+    pub fn push_native(&mut self, string: &ParameterString<'_>) {
+        autoreleasepool(|pool| self.append(string.nsstring(), pool))
+    }
+    fn append(&mut self, string: &NSString, pool: &ActiveAutoreleasePool) {
+        unsafe {
+            //the builder holds the only reference, so it's ours to mutate
+            let _: () = NSMutableString::perform_primitive(self.string.assume_nonmut_perform(), Sel::appendString_(), pool, (string.assume_nonmut_perform(),));
+        }
+    }
+    ///Finishes the string.
+    pub fn finish(self) -> OwnedString {
+        //nothing can mutate the string once the builder is gone
+        OwnedString(unsafe{self.string.cast_into()})
+    }
+    ///Finishes the string.  This never fails on macOS.
+    pub fn try_finish(self) -> Result<OwnedString, Error> {
+        Ok(self.finish())
+    }
+}
+impl Default for OwnedStringBuilder {
+    fn default() -> Self {
+        OwnedStringBuilder::new()
+    }
+}
+impl Write for OwnedStringBuilder {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+impl Extend<char> for OwnedStringBuilder {
+    fn extend<I: IntoIterator<Item=char>>(&mut self, iter: I) {
+        //one append is cheaper than one per character
+        let string: String = iter.into_iter().collect();
+        self.push_str(&string);
+    }
+}
+impl<'a> Extend<&'a str> for OwnedStringBuilder {
+    fn extend<I: IntoIterator<Item=&'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.push_str(s);
+        }
+    }
+}
+
+/**
+Type that can be created from a platform string.  This is the reverse of [IntoParameterString].
+
+Binding authors can return any conforming type, and let the caller choose:
+
+```
+use pcore::string::{FromPlatformString, IntoParameterString};
+use pcore::release_pool::ReleasePool;
+use pcore::error::Error;
+fn name<T: FromPlatformString>(pool: &ReleasePool) -> Result<T, Error> {
+    //in a real binding, this would be the result of an OS API
+    let parameter_string = "name".into_parameter_string(pool);
+    T::from_parameter_string(parameter_string, pool)
+}
+let pool = unsafe{ReleasePool::new()};
+let name: String = name(&pool).unwrap();
+assert_eq!(name, "name");
+```
+
+Conversions fail if the string can't be represented in the output type, such as an unpaired surrogate in a [String].
+
+Implementing it only requires converting from a borrowed [NativeStr], which is the same on every platform:
+
+```
+use pcore::string::{FromPlatformString, NativeStr};
+use pcore::error::Error;
+struct Shouted(String);
+impl FromPlatformString for Shouted {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        view.try_to_string().map(|s| Shouted(s.to_uppercase()))
+    }
+}
+assert_eq!(Shouted::from_native_str(&pcore::pstr!("hi")).unwrap().0, "HI");
+```
+*/
+pub trait FromPlatformString: Sized {
+    ///Converts from a parameter string, reusing its storage where possible.
+    fn from_parameter_string(string: ParameterString<'_>, _pool: &ActiveAutoreleasePool) -> Result<Self, Error> {
+        Self::from_native_str(&string)
+    }
+    ///Converts from a borrowed platform string.
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error>;
+}
+impl FromPlatformString for String {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        view.try_to_string()
+    }
+}
+impl FromPlatformString for Box<str> {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        String::from_native_str(view).map(String::into_boxed_str)
+    }
+}
+///macOS paths can't contain unpaired surrogates, so those fail.
+impl FromPlatformString for OsString {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        String::from_native_str(view).map(OsString::from)
+    }
+}
+impl FromPlatformString for PathBuf {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        OsString::from_native_str(view).map(PathBuf::from)
+    }
+}
+impl FromPlatformString for OwnedString {
+    fn from_parameter_string(string: ParameterString<'_>, pool: &ActiveAutoreleasePool) -> Result<Self, Error> {
+        Ok(OwnedString::new(string, pool))
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        Ok(view.to_owned())
+    }
+}
 
 //need to re-export this so it's usable from our macro...
 #[doc(hidden)]
@@ -200,4 +977,153 @@ macro_rules! pstr {
         })
     }
     thunk(f);
-}
\ No newline at end of file
+}
+#[test] fn read_back() {
+    autoreleasepool(|pool| {
+        let owned = OwnedString::new("héllo 😀", pool);
+        assert_eq!(owned.try_to_string().unwrap(), "héllo 😀");
+        assert_eq!(owned.len_utf8(), "héllo 😀".len());
+        assert_eq!(owned.len_utf16(), 8);
+        assert!(!owned.is_empty());
+        assert_eq!(owned.chars().count(), 7);
+        assert!("".into_parameter_string(pool).is_empty());
+    })
+}
+#[test] fn traits() {
+    autoreleasepool(|pool| {
+        let owned = OwnedString::new("hello", pool);
+        assert_eq!(owned.clone(), owned);
+        assert_eq!(owned, "hello");
+        assert_eq!(owned, *"hello");
+        assert_ne!(owned, "hell");
+        assert!(OwnedString::new("a", pool) < OwnedString::new("ab", pool));
+        assert!(OwnedString::default().is_empty());
+        assert_eq!(OwnedString::default(), "");
+        assert_eq!(pstr!("hello"), "hello");
+        assert_eq!(format!("{:?}", pstr!("hello")), "hello");
+        assert_eq!(PStr::default(), "");
+    })
+}
+#[test] fn consistent_with_str() {
+    use std::collections::hash_map::DefaultHasher;
+    fn hash<T: Hash + ?Sized>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+    autoreleasepool(|pool| {
+        //U+FF61 sorts after U+1F600 by UTF-16 code unit, but before it by scalar value
+        let strs = ["", "a", "ab", "b", "héllo", "\u{FF61}", "😀", &"x".repeat(300), &"héllo 😀".repeat(60), &("x".to_owned() + &"😀".repeat(200))];
+        for a in strs {
+            let owned = OwnedString::new(a, pool);
+            assert_eq!(hash(&owned), hash(a));
+            assert_eq!(hash(&a.into_parameter_string(pool)), hash(a));
+            for b in strs {
+                assert_eq!(owned.cmp(&OwnedString::new(b, pool)), a.cmp(b), "{:?} {:?}", a, b);
+            }
+        }
+        //pstr! only supports ASCII on macOS
+        assert_eq!(hash(&pstr!("hello")), hash("hello"));
+        assert!(pstr!("a") < pstr!("ab"));
+    })
+}
+#[test] fn std_types() {
+    fn check<'a, S: IntoParameterString<'a>>(s: S, pool: &ActiveAutoreleasePool) {
+        assert_eq!(OwnedString(s.into_nsstring(pool).copy(pool)), "hi");
+    }
+    autoreleasepool(|pool| {
+        let string = String::from("hi");
+        check(&string, pool);
+        check(Box::<str>::from("hi"), pool);
+        check(Rc::<str>::from("hi"), pool);
+        check(Arc::<str>::from("hi"), pool);
+        check(Cow::Borrowed("hi"), pool);
+        check(Cow::<str>::Owned("hi".to_owned()), pool);
+        check(OsStr::new("hi"), pool);
+        check(OsString::from("hi"), pool);
+        check(PathBuf::from("hi"), pool);
+        check(Path::new("hi"), pool);
+        check(CString::new("hi").unwrap(), pool);
+        check(CString::new("hi").unwrap().as_c_str(), pool);
+        assert_eq!(OwnedString::new('é', pool), "é");
+        assert!(CString::new(vec![0xFF]).unwrap().try_into_parameter_string(pool).is_err());
+    })
+}
+#[test] fn from_platform_string() {
+    fn check<T: FromPlatformString>(pool: &ActiveAutoreleasePool) -> T {
+        T::from_parameter_string("hi".into_parameter_string(pool), pool).unwrap()
+    }
+    autoreleasepool(|pool| {
+        assert_eq!(check::<String>(pool), "hi");
+        assert_eq!(&*check::<Box<str>>(pool), "hi");
+        assert_eq!(check::<OsString>(pool), "hi");
+        assert_eq!(check::<PathBuf>(pool), Path::new("hi"));
+        assert_eq!(check::<OwnedString>(pool), "hi");
+    })
+}
+
+#[test] fn pformat() {
+    let name = "wörld";
+    assert_eq!(crate::pformat!("hello {}", name), "hello wörld");
+    assert_eq!(crate::pformat!("{{static}} wörld"), "{static} wörld");
+    autoreleasepool(|pool| {
+        assert_eq!(format_args!("{}-{}", 1, 2).into_parameter_string(pool).to_string_lossy(), "1-2");
+        assert_eq!(format_args!("piece").into_parameter_string(pool).to_string_lossy(), "piece");
+    })
+}
+
+#[test] fn pformat_records_conversion() {
+    crate::release_pool::autoreleasepool(|pool| {
+        let before = pool.innermost_statistics().conversions;
+        let _ = crate::pformat!("hello {}", 1);
+        assert_eq!(pool.innermost_statistics().conversions, before + 1);
+        let _ = format_args!("piece").into_parameter_string(pool);
+        assert_eq!(pool.innermost_statistics().conversions, before + 2);
+    })
+}
+
+#[test] fn builder() {
+    let mut builder = OwnedStringBuilder::with_capacity(4);
+    builder.push_str("héllo");
+    builder.push(' ');
+    builder.push('😀');
+    builder.reserve(16);
+    autoreleasepool(|pool| {
+        builder.push_native(&pstr!(" world").into_parameter_string(pool));
+    });
+    builder.extend(['!', '?']);
+    builder.extend([" a", "b"]);
+    write!(builder, " {}", 42).unwrap();
+    let owned = builder.finish();
+    assert_eq!(owned, "héllo 😀 world!? ab 42");
+    assert_eq!(OwnedStringBuilder::new().finish(), "");
+    let mut builder = OwnedStringBuilder::new();
+    builder.reserve(8);
+    builder.push_str("reserved");
+    builder.reserve(1);
+    builder.push('!');
+    assert_eq!(builder.finish(), "reserved!");
+}
+
+#[test] fn native_str() {
+    use std::collections::HashMap;
+    autoreleasepool(|pool| {
+        let owned = OwnedString::new("héllo", pool);
+        let native: &NativeStr = &owned;
+        assert_eq!(native, "héllo");
+        assert_eq!(native.len_utf8(), 6);
+        assert_eq!(native.to_string(), "héllo");
+        assert_eq!(&*"héllo".into_parameter_string(pool), native);
+        assert_eq!(&*pstr!("hi"), "hi");
+        assert_eq!(native.to_owned(), owned);
+        assert_eq!(OwnedString::new(native, pool), owned);
+        let mut map = HashMap::new();
+        map.insert(owned.clone(), 1);
+        assert_eq!(map.get(native), Some(&1));
+        fn len<S: AsRef<NativeStr>>(string: S) -> usize {
+            string.as_ref().len_utf16()
+        }
+        assert_eq!(len(&owned), 5);
+        assert_eq!(len(pstr!("hi")), 2);
+    })
+}
@@ -0,0 +1,157 @@
+use super::{NativeStr, OwnedString, PStr};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/**
+A platform string that is either borrowed or owned, like [Cow](std::borrow::Cow) for [NativeStr].
+
+This suits bindings that sometimes return static or caller-owned text, and sometimes allocate.
+
+```
+use pcore::string::{MaybeOwnedString, OwnedString};
+use pcore::release_pool::autoreleasepool;
+fn name(custom: Option<OwnedString>) -> MaybeOwnedString<'static> {
+    match custom {
+        Some(custom) => custom.into(),
+        None => pcore::pstr!("untitled").into(),
+    }
+}
+assert_eq!(name(None), "untitled");
+assert_eq!(name(Some(autoreleasepool(|pool| OwnedString::new("notes", pool)))), "notes");
+```
+*/
+#[derive(Clone)]
+pub enum MaybeOwnedString<'a> {
+    ///Text borrowed from static or caller storage.
+    Borrowed(&'a NativeStr),
+    ///A string allocated for this value.
+    Owned(OwnedString),
+}
+impl<'a> MaybeOwnedString<'a> {
+    ///Extracts the owned string, copying it only if it's borrowed.
+    pub fn into_owned(self) -> OwnedString {
+        match self {
+            MaybeOwnedString::Borrowed(string) => string.to_owned(),
+            MaybeOwnedString::Owned(string) => string,
+        }
+    }
+}
+
+impl<'a> From<&'a NativeStr> for MaybeOwnedString<'a> {
+    fn from(string: &'a NativeStr) -> Self {
+        MaybeOwnedString::Borrowed(string)
+    }
+}
+impl<'a> From<&'a OwnedString> for MaybeOwnedString<'a> {
+    fn from(string: &'a OwnedString) -> Self {
+        MaybeOwnedString::Borrowed(string)
+    }
+}
+impl From<PStr> for MaybeOwnedString<'static> {
+    fn from(string: PStr) -> Self {
+        MaybeOwnedString::Borrowed(string.native_str())
+    }
+}
+impl From<OwnedString> for MaybeOwnedString<'_> {
+    fn from(string: OwnedString) -> Self {
+        MaybeOwnedString::Owned(string)
+    }
+}
+impl<'a> From<MaybeOwnedString<'a>> for OwnedString {
+    fn from(string: MaybeOwnedString<'a>) -> Self {
+        string.into_owned()
+    }
+}
+
+impl Deref for MaybeOwnedString<'_> {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        match self {
+            MaybeOwnedString::Borrowed(string) => string,
+            MaybeOwnedString::Owned(string) => string,
+        }
+    }
+}
+impl AsRef<NativeStr> for MaybeOwnedString<'_> {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Borrow<NativeStr> for MaybeOwnedString<'_> {
+    fn borrow(&self) -> &NativeStr {
+        self
+    }
+}
+impl std::fmt::Display for MaybeOwnedString<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+impl std::fmt::Debug for MaybeOwnedString<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+///Compares the text, whether it's borrowed or owned.
+impl PartialEq for MaybeOwnedString<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+impl Eq for MaybeOwnedString<'_> {}
+///Hashes the same as the equivalent [str].
+impl Hash for MaybeOwnedString<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+impl PartialOrd for MaybeOwnedString<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for MaybeOwnedString<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+impl PartialEq<str> for MaybeOwnedString<'_> {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+impl PartialEq<&str> for MaybeOwnedString<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+#[test] fn copies_only_when_borrowed() {
+    use crate::release_pool::autoreleasepool;
+    use super::IntoParameterString;
+    autoreleasepool(|pool| {
+        let owned = OwnedString::new("héllo", pool);
+        let address = &*owned as *const NativeStr;
+        let borrowed = MaybeOwnedString::from(&owned);
+        assert!(matches!(borrowed, MaybeOwnedString::Borrowed(_)));
+        assert_eq!(borrowed, "héllo");
+        let copy = borrowed.clone().into_owned();
+        assert_eq!(copy, owned);
+        assert_eq!(MaybeOwnedString::from(owned.clone()), borrowed);
+
+        let moved = MaybeOwnedString::from(owned).into_owned();
+        assert!(std::ptr::eq(&*moved, address));
+
+        let pstr = MaybeOwnedString::from(crate::pstr!("static"));
+        assert!(matches!(pstr, MaybeOwnedString::Borrowed(_)));
+        assert_eq!(&*(&pstr).into_parameter_string(pool), "static");
+        assert_eq!(&*pstr.into_parameter_string(pool), "static");
+
+        let parameter_string = "hi".into_parameter_string(pool);
+        assert_eq!(MaybeOwnedString::from(parameter_string).into_owned(), "hi");
+    })
+}
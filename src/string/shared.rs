@@ -0,0 +1,132 @@
+use super::{NativeStr, OwnedString};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+#[cfg(not(target_os = "macos"))]
+use std::sync::Arc;
+
+/**
+A reference-counted platform string, for sharing one string in many places.
+
+Cloning a [SharedString] only adjusts a reference count, and it can be sent between threads.  It converts from
+an [OwnedString] without copying, and into a [ParameterString](super::ParameterString) without copying or converting.
+
+```
+use pcore::string::{OwnedString, SharedString};
+use pcore::release_pool::autoreleasepool;
+let label = SharedString::from(autoreleasepool(|pool| OwnedString::new("OK", pool)));
+let copy = label.clone();
+std::thread::spawn(move || assert_eq!(copy, "OK")).join().unwrap();
+```
+*/
+#[derive(Clone)]
+pub struct SharedString(
+    //an `Arc` on the platforms where cloning an OwnedString copies it
+    #[cfg(not(target_os = "macos"))]
+    pub(super) Arc<OwnedString>,
+    //cloning an NSString-backed OwnedString is already a retain
+    #[cfg(target_os = "macos")]
+    pub(super) OwnedString,
+);
+
+//the NSString is never mutated once it's in an OwnedString, and immutable Foundation strings are thread-safe
+#[cfg(target_os = "macos")]
+unsafe impl Send for SharedString {}
+#[cfg(target_os = "macos")]
+unsafe impl Sync for SharedString {}
+
+impl From<OwnedString> for SharedString {
+    fn from(string: OwnedString) -> Self {
+        #[cfg(not(target_os = "macos"))]
+        return SharedString(Arc::new(string));
+        #[cfg(target_os = "macos")]
+        return SharedString(string);
+    }
+}
+impl Deref for SharedString {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        &self.0
+    }
+}
+impl AsRef<NativeStr> for SharedString {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Borrow<NativeStr> for SharedString {
+    fn borrow(&self) -> &NativeStr {
+        self
+    }
+}
+impl std::fmt::Display for SharedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+impl std::fmt::Debug for SharedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+impl PartialEq for SharedString {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+impl Eq for SharedString {}
+///Hashes the same as the equivalent [str].
+impl Hash for SharedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+impl PartialOrd for SharedString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for SharedString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+impl PartialEq<str> for SharedString {
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+impl PartialEq<&str> for SharedString {
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+#[test] fn shares_storage() {
+    use crate::release_pool::autoreleasepool;
+    use super::IntoParameterString;
+    fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<SharedString>();
+    autoreleasepool(|pool| {
+        let owned = OwnedString::new("héllo", pool);
+        let address = &*owned as *const NativeStr;
+        let shared = SharedString::from(owned);
+        let copy = shared.clone();
+        assert!(std::ptr::eq(&*shared, address));
+        assert!(std::ptr::eq(&*copy, address));
+        assert_eq!(copy, "héllo");
+        assert_eq!(copy, shared);
+        let before = pool.innermost_statistics().conversions;
+        assert_eq!(&*(&shared).into_parameter_string(pool), "héllo");
+        assert_eq!(&*shared.clone().into_parameter_string(pool), "héllo");
+        assert_eq!(pool.innermost_statistics().conversions, before);
+        //the parameter string keeps the storage alive
+        let parameter_string = copy.into_parameter_string(pool);
+        drop(shared);
+        assert_eq!(&*parameter_string, "héllo");
+        assert_eq!(OwnedString::new(parameter_string, pool), "héllo");
+    })
+}
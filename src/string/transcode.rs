@@ -0,0 +1,541 @@
+/*!
+Transcoding between UTF-8 and UTF-16.
+
+Runs of ASCII are transcoded a block at a time with SIMD: SSE2 on x86_64, or AVX2 where the CPU has it, and NEON
+on aarch64.  The instruction set is detected once, at runtime.  Everything else goes through scalar code, as does
+everything on other architectures.
+
+Text that isn't ASCII tends to stay that way, so after a block that isn't all ASCII, the scalar code transcodes
+a stretch of [STRETCH] units before SIMD is tried again.
+*/
+//the UTF-8 backends only decode, or don't transcode at all
+#![cfg_attr(not(any(target_os = "windows", all(feature = "utf16-backend", not(target_os = "macos")))), allow(dead_code))]
+
+use crate::error::Error;
+use std::mem::MaybeUninit;
+use std::sync::OnceLock;
+
+///Units transcoded by the scalar code between attempts at SIMD.
+const STRETCH: usize = 64;
+///The smallest block any kernel transcodes, so there's no point calling one with less.
+const MIN_BLOCK: usize = 16;
+
+/**
+Transcodes runs of ASCII with one instruction set.
+
+Each function transcodes whole blocks from the start of `src` to the start of `dst`, stopping at the first block
+that isn't all ASCII or once less than a block remains in either, and returns the number of units transcoded.
+
+The functions may only be called if the CPU supports the instruction set.
+*/
+#[derive(Clone, Copy)]
+struct Kernel {
+    widen: unsafe fn(&[u8], &mut [MaybeUninit<u16>]) -> usize,
+    narrow: unsafe fn(&[u16], &mut [MaybeUninit<u8>]) -> usize,
+}
+
+const SCALAR: Kernel = Kernel { widen: scalar::widen, narrow: scalar::narrow };
+#[cfg(target_arch = "x86_64")]
+const SSE2: Kernel = Kernel { widen: x86::widen_sse2, narrow: x86::narrow_sse2 };
+#[cfg(target_arch = "x86_64")]
+const AVX2: Kernel = Kernel { widen: x86::widen_avx2, narrow: x86::narrow_avx2 };
+#[cfg(target_arch = "aarch64")]
+const NEON: Kernel = Kernel { widen: neon::widen, narrow: neon::narrow };
+
+///The fastest kernel the CPU supports.
+fn kernel() -> Kernel {
+    static KERNEL: OnceLock<Kernel> = OnceLock::new();
+    *KERNEL.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return AVX2;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return SSE2;
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                return NEON;
+            }
+        }
+        SCALAR
+    })
+}
+
+///Blocks of 16 units, in loops the compiler can vectorise for whatever the target has.
+mod scalar {
+    use std::mem::MaybeUninit;
+    const BLOCK: usize = 16;
+
+    pub(super) fn widen(src: &[u8], dst: &mut [MaybeUninit<u16>]) -> usize {
+        let mut done = 0;
+        for (src, dst) in src.chunks_exact(BLOCK).zip(dst.chunks_exact_mut(BLOCK)) {
+            if src.iter().fold(0, |bits, &byte| bits | byte) >= 0x80 {
+                break;
+            }
+            for (unit, &byte) in dst.iter_mut().zip(src) {
+                unit.write(u16::from(byte));
+            }
+            done += BLOCK;
+        }
+        done
+    }
+    pub(super) fn narrow(src: &[u16], dst: &mut [MaybeUninit<u8>]) -> usize {
+        let mut done = 0;
+        for (src, dst) in src.chunks_exact(BLOCK).zip(dst.chunks_exact_mut(BLOCK)) {
+            if src.iter().fold(0, |bits, &unit| bits | unit) >= 0x80 {
+                break;
+            }
+            for (byte, &unit) in dst.iter_mut().zip(src) {
+                byte.write(unit as u8);
+            }
+            done += BLOCK;
+        }
+        done
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+    use std::mem::MaybeUninit;
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn widen_sse2(src: &[u8], dst: &mut [MaybeUninit<u16>]) -> usize {
+        const BLOCK: usize = 16;
+        let blocks = src.len().min(dst.len()) / BLOCK;
+        let zero = _mm_setzero_si128();
+        for block in 0..blocks {
+            let bytes = _mm_loadu_si128(src.as_ptr().add(block * BLOCK) as *const __m128i);
+            if _mm_movemask_epi8(bytes) != 0 {
+                return block * BLOCK;
+            }
+            let out = dst.as_mut_ptr().add(block * BLOCK) as *mut __m128i;
+            _mm_storeu_si128(out, _mm_unpacklo_epi8(bytes, zero));
+            _mm_storeu_si128(out.add(1), _mm_unpackhi_epi8(bytes, zero));
+        }
+        blocks * BLOCK
+    }
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn narrow_sse2(src: &[u16], dst: &mut [MaybeUninit<u8>]) -> usize {
+        const BLOCK: usize = 16;
+        let blocks = src.len().min(dst.len()) / BLOCK;
+        let non_ascii = _mm_set1_epi16(0xFF80u16 as i16);
+        let zero = _mm_setzero_si128();
+        for block in 0..blocks {
+            let input = src.as_ptr().add(block * BLOCK) as *const __m128i;
+            let low = _mm_loadu_si128(input);
+            let high = _mm_loadu_si128(input.add(1));
+            let bits = _mm_and_si128(_mm_or_si128(low, high), non_ascii);
+            if _mm_movemask_epi8(_mm_cmpeq_epi16(bits, zero)) != 0xFFFF {
+                return block * BLOCK;
+            }
+            _mm_storeu_si128(dst.as_mut_ptr().add(block * BLOCK) as *mut __m128i, _mm_packus_epi16(low, high));
+        }
+        blocks * BLOCK
+    }
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn widen_avx2(src: &[u8], dst: &mut [MaybeUninit<u16>]) -> usize {
+        const BLOCK: usize = 32;
+        let blocks = src.len().min(dst.len()) / BLOCK;
+        for block in 0..blocks {
+            let bytes = _mm256_loadu_si256(src.as_ptr().add(block * BLOCK) as *const __m256i);
+            if _mm256_movemask_epi8(bytes) != 0 {
+                return block * BLOCK;
+            }
+            let out = dst.as_mut_ptr().add(block * BLOCK) as *mut __m256i;
+            _mm256_storeu_si256(out, _mm256_cvtepu8_epi16(_mm256_castsi256_si128(bytes)));
+            _mm256_storeu_si256(out.add(1), _mm256_cvtepu8_epi16(_mm256_extracti128_si256(bytes, 1)));
+        }
+        blocks * BLOCK
+    }
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn narrow_avx2(src: &[u16], dst: &mut [MaybeUninit<u8>]) -> usize {
+        const BLOCK: usize = 32;
+        let blocks = src.len().min(dst.len()) / BLOCK;
+        let non_ascii = _mm256_set1_epi16(0xFF80u16 as i16);
+        for block in 0..blocks {
+            let input = src.as_ptr().add(block * BLOCK) as *const __m256i;
+            let low = _mm256_loadu_si256(input);
+            let high = _mm256_loadu_si256(input.add(1));
+            if _mm256_testz_si256(_mm256_or_si256(low, high), non_ascii) == 0 {
+                return block * BLOCK;
+            }
+            //packing works within 128-bit lanes, so put the lanes back in order
+            let packed = _mm256_permute4x64_epi64(_mm256_packus_epi16(low, high), 0b11_01_10_00);
+            _mm256_storeu_si256(dst.as_mut_ptr().add(block * BLOCK) as *mut __m256i, packed);
+        }
+        blocks * BLOCK
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+    use std::mem::MaybeUninit;
+    const BLOCK: usize = 16;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn widen(src: &[u8], dst: &mut [MaybeUninit<u16>]) -> usize {
+        let blocks = src.len().min(dst.len()) / BLOCK;
+        for block in 0..blocks {
+            let bytes = vld1q_u8(src.as_ptr().add(block * BLOCK));
+            if vmaxvq_u8(bytes) >= 0x80 {
+                return block * BLOCK;
+            }
+            let out = dst.as_mut_ptr().add(block * BLOCK) as *mut u16;
+            vst1q_u16(out, vmovl_u8(vget_low_u8(bytes)));
+            vst1q_u16(out.add(8), vmovl_high_u8(bytes));
+        }
+        blocks * BLOCK
+    }
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn narrow(src: &[u16], dst: &mut [MaybeUninit<u8>]) -> usize {
+        let blocks = src.len().min(dst.len()) / BLOCK;
+        for block in 0..blocks {
+            let input = src.as_ptr().add(block * BLOCK);
+            let low = vld1q_u16(input);
+            let high = vld1q_u16(input.add(8));
+            if vmaxvq_u16(vorrq_u16(low, high)) >= 0x80 {
+                return block * BLOCK;
+            }
+            vst1q_u8(dst.as_mut_ptr().add(block * BLOCK) as *mut u8, vcombine_u8(vmovn_u16(low), vmovn_u16(high)));
+        }
+        blocks * BLOCK
+    }
+}
+
+///The number of UTF-16 units that encode `s`.
+pub(crate) fn utf16_len(s: &str) -> usize {
+    //one unit per lead byte, and another for lead bytes of 4-byte sequences (which encode surrogate pairs).
+    //Counting each chunk in a u8, which can't overflow, lets the compiler vectorise the loop.
+    s.as_bytes().chunks(127).map(|chunk| {
+        usize::from(chunk.iter().fold(0u8, |len, &b| len + u8::from(b & 0xC0 != 0x80) + u8::from(b >= 0xF0)))
+    }).sum()
+}
+///The number of UTF-8 bytes that encode `units`, replacing unpaired surrogates with U+FFFD.
+pub(crate) fn utf8_len_lossy(units: &[u16]) -> usize {
+    //a surrogate pair takes 2 bytes per surrogate, and U+FFFD takes 3
+    utf8_len(units) + unpaired_surrogates(units)
+}
+///The number of UTF-8 bytes that encode `units`, counting 2 for each surrogate.
+fn utf8_len(units: &[u16]) -> usize {
+    units.chunks(64).map(|chunk| {
+        usize::from(chunk.iter().fold(0u8, |len, &u| {
+            len + if u & 0xF800 == 0xD800 { 2 } else { 1 + u8::from(u >= 0x80) + u8::from(u >= 0x800) }
+        }))
+    }).sum()
+}
+fn unpaired_surrogates(units: &[u16]) -> usize {
+    let mut unpaired = 0;
+    let mut read = 0;
+    while let Some(offset) = units[read..].iter().position(|&u| u & 0xF800 == 0xD800) {
+        read += offset;
+        match units.get(read + 1) {
+            Some(&low) if units[read] < 0xDC00 && low & 0xFC00 == 0xDC00 => read += 2,
+            _ => {
+                unpaired += 1;
+                read += 1;
+            }
+        }
+    }
+    unpaired
+}
+
+///Encodes `s` into the start of `dst`, and returns the number of units written.
+///
+/// # Panics
+/// If `dst` has room for fewer than [utf16_len] units.
+pub(crate) fn encode_utf16(s: &str, dst: &mut [MaybeUninit<u16>]) -> usize {
+    encode_with(kernel(), s, dst)
+}
+///Encodes `s` into the start of `dst`, like [encode_utf16].
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub(crate) fn encode_utf16_into(s: &str, dst: &mut [u16]) -> usize {
+    //only initialized values are written
+    encode_utf16(s, unsafe{&mut *(dst as *mut [u16] as *mut [MaybeUninit<u16>])})
+}
+///Appends the encoding of `s` to `dst`.
+pub(crate) fn push_utf16(dst: &mut Vec<u16>, s: &str) {
+    //a str never has fewer bytes than UTF-16 units
+    dst.reserve(s.len());
+    let len = encode_utf16(s, dst.spare_capacity_mut());
+    unsafe{dst.set_len(dst.len() + len)}
+}
+///Decodes `units`, or fails if they contain an unpaired surrogate.
+pub(crate) fn try_decode_utf16(units: &[u16]) -> Result<String, Error> {
+    decode(units, false).ok_or_else(Error::invalid_encoding)
+}
+///Decodes `units`, replacing unpaired surrogates with U+FFFD.
+pub(crate) fn decode_utf16_lossy(units: &[u16]) -> String {
+    decode(units, true).expect("lossy decoding failed")
+}
+///Decodes `units` into the start of `dst`, replacing unpaired surrogates with U+FFFD.
+///
+/// # Panics
+/// If `dst` has room for fewer than [utf8_len_lossy] bytes.
+pub(crate) fn decode_utf16_lossy_into<'a>(units: &[u16], dst: &'a mut [MaybeUninit<u8>]) -> &'a str {
+    let len = decode_with(kernel(), units, true, dst).expect("lossy decoding failed");
+    unsafe {
+        //decode_with initializes what it writes, and only writes whole characters
+        std::str::from_utf8_unchecked(&*(&dst[..len] as *const [MaybeUninit<u8>] as *const [u8]))
+    }
+}
+///Decodes `units` a stack buffer at a time, replacing unpaired surrogates with U+FFFD, and passes each piece to `f`.
+pub(crate) fn decode_utf16_lossy_pieces<E, F: FnMut(&str) -> Result<(), E>>(units: &[u16], mut f: F) -> Result<(), E> {
+    const PIECE: usize = 256;
+    let mut buffer = [MaybeUninit::uninit(); PIECE * 3];
+    let mut rest = units;
+    while !rest.is_empty() {
+        let mut end = rest.len().min(PIECE);
+        //keep surrogate pairs in one piece
+        if end < rest.len() && rest[end - 1] & 0xFC00 == 0xD800 {
+            end -= 1;
+        }
+        let (piece, next) = rest.split_at(end);
+        f(decode_utf16_lossy_into(piece, &mut buffer))?;
+        rest = next;
+    }
+    Ok(())
+}
+///Whether `units` encode `s`.  Only `s` is transcoded, a stack buffer at a time.
+pub(crate) fn eq_utf16(units: &[u16], s: &str) -> bool {
+    if units.len() != utf16_len(s) {
+        return false;
+    }
+    let mut buffer = [MaybeUninit::uninit(); 64];
+    let mut rest = units;
+    let mut start = 0;
+    while start < s.len() {
+        //a piece of up to 64 bytes encodes to at most 64 units
+        let mut end = (start + buffer.len()).min(s.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        let len = encode_utf16(&s[start..end], &mut buffer);
+        //encode_utf16 initializes what it writes
+        let encoded = unsafe{&*(&buffer[..len] as *const [MaybeUninit<u16>] as *const [u16])};
+        //the lengths match overall, so `rest` has at least `len` units
+        let (piece, next) = rest.split_at(len);
+        if piece != encoded {
+            return false;
+        }
+        rest = next;
+        start = end;
+    }
+    true
+}
+fn decode(units: &[u16], lossy: bool) -> Option<String> {
+    let mut bytes = Vec::with_capacity(if lossy { utf8_len_lossy(units) } else { utf8_len(units) });
+    let len = decode_with(kernel(), units, lossy, bytes.spare_capacity_mut())?;
+    unsafe {
+        bytes.set_len(len);
+        //decode_with only writes whole characters
+        Some(String::from_utf8_unchecked(bytes))
+    }
+}
+
+fn encode_with(kernel: Kernel, s: &str, dst: &mut [MaybeUninit<u16>]) -> usize {
+    let bytes = s.as_bytes();
+    let mut read = 0;
+    let mut written = 0;
+    while read < bytes.len() {
+        if bytes.len() - read >= MIN_BLOCK {
+            let ascii = unsafe{(kernel.widen)(&bytes[read..], &mut dst[written..])};
+            read += ascii;
+            written += ascii;
+        }
+        let mut end = (read + STRETCH).min(bytes.len());
+        while !s.is_char_boundary(end) {
+            end += 1;
+        }
+        let stretch = &bytes[read..end];
+        let room = dst.len();
+        let mut src = stretch.iter();
+        let mut out = dst[written..].iter_mut();
+        let mut write = |unit: u16| out.next().expect("UTF-16 buffer is too small").write(unit);
+        //`s` is valid UTF-8, so every sequence is complete
+        let continuation = |src: &mut std::slice::Iter<'_, u8>| u32::from(src.next().unwrap() & 0x3F);
+        while let Some(&lead) = src.next() {
+            if lead < 0x80 {
+                write(u16::from(lead));
+            }
+            else if lead < 0xE0 {
+                write(((u32::from(lead & 0x1F) << 6) | continuation(&mut src)) as u16);
+            }
+            else if lead < 0xF0 {
+                write(((u32::from(lead & 0x0F) << 12) | (continuation(&mut src) << 6) | continuation(&mut src)) as u16);
+            }
+            else {
+                let c = ((u32::from(lead & 0x07) << 18) | (continuation(&mut src) << 12) | (continuation(&mut src) << 6) | continuation(&mut src)) - 0x10000;
+                write(0xD800 | (c >> 10) as u16);
+                write(0xDC00 | (c & 0x3FF) as u16);
+            }
+        }
+        written = room - out.len();
+        read = end;
+    }
+    written
+}
+///Decodes `units` into the start of `dst`, and returns the number of bytes written.  Returns `None` if not `lossy`
+/// and `units` contain an unpaired surrogate.
+///
+/// # Panics
+/// If `dst` is too small.
+fn decode_with(kernel: Kernel, units: &[u16], lossy: bool, dst: &mut [MaybeUninit<u8>]) -> Option<usize> {
+    let mut read = 0;
+    let mut written = 0;
+    while read < units.len() {
+        if units.len() - read >= MIN_BLOCK {
+            let ascii = unsafe{(kernel.narrow)(&units[read..], &mut dst[written..])};
+            read += ascii;
+            written += ascii;
+        }
+        //a surrogate pair may run one past the end
+        let end = (read + STRETCH).min(units.len());
+        while read < end {
+            let unit = units[read];
+            read += 1;
+            let c = match unit {
+                0..=0x7F => {
+                    dst[written].write(unit as u8);
+                    written += 1;
+                    continue;
+                }
+                0x80..=0x7FF => {
+                    dst[written].write(0xC0 | (unit >> 6) as u8);
+                    dst[written + 1].write(0x80 | (unit & 0x3F) as u8);
+                    written += 2;
+                    continue;
+                }
+                0xD800..=0xDBFF => match units.get(read) {
+                    Some(&low) if (0xDC00..0xE000).contains(&low) => {
+                        read += 1;
+                        0x10000 + ((u32::from(unit) - 0xD800) << 10) + (u32::from(low) - 0xDC00)
+                    }
+                    _ if lossy => 0xFFFD,
+                    _ => return None,
+                },
+                0xDC00..=0xDFFF if lossy => 0xFFFD,
+                0xDC00..=0xDFFF => return None,
+                _ => u32::from(unit),
+            };
+            if c < 0x10000 {
+                dst[written].write(0xE0 | (c >> 12) as u8);
+                dst[written + 1].write(0x80 | ((c >> 6) & 0x3F) as u8);
+                dst[written + 2].write(0x80 | (c & 0x3F) as u8);
+                written += 3;
+            }
+            else {
+                dst[written].write(0xF0 | (c >> 18) as u8);
+                dst[written + 1].write(0x80 | ((c >> 12) & 0x3F) as u8);
+                dst[written + 2].write(0x80 | ((c >> 6) & 0x3F) as u8);
+                dst[written + 3].write(0x80 | (c & 0x3F) as u8);
+                written += 4;
+            }
+        }
+    }
+    Some(written)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    ///Every kernel the CPU supports.
+    fn kernels() -> Vec<Kernel> {
+        #[allow(unused_mut)]
+        let mut kernels = vec![SCALAR];
+        #[cfg(target_arch = "x86_64")]
+        {
+            kernels.push(SSE2);
+            if is_x86_feature_detected!("avx2") {
+                kernels.push(AVX2);
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        kernels.push(NEON);
+        kernels
+    }
+
+    fn encode(kernel: Kernel, s: &str) -> Vec<u16> {
+        let mut units = Vec::with_capacity(utf16_len(s));
+        let len = encode_with(kernel, s, units.spare_capacity_mut());
+        unsafe{units.set_len(len)};
+        units
+    }
+    fn decode(kernel: Kernel, units: &[u16], lossy: bool) -> Option<String> {
+        //exactly the right capacity, which checks the lengths too
+        let mut bytes = Vec::with_capacity(if lossy { utf8_len_lossy(units) } else { utf8_len(units) });
+        let len = decode_with(kernel, units, lossy, bytes.spare_capacity_mut())?;
+        unsafe{bytes.set_len(len)};
+        Some(String::from_utf8(bytes).unwrap())
+    }
+
+    ///Text that mixes runs of ASCII, which take the SIMD paths, with everything else.
+    fn text() -> impl Strategy<Value=String> {
+        prop::collection::vec(prop_oneof!["[ -~]{0,80}", ".{0,20}", "[à-ÿ]{0,10}", "[😀-🙏]{0,5}"], 0..8).prop_map(|runs| runs.concat())
+    }
+    ///UTF-16 that mixes text with unpaired surrogates.
+    fn units() -> impl Strategy<Value=Vec<u16>> {
+        prop::collection::vec(prop_oneof![
+            text().prop_map(|s| s.encode_utf16().collect::<Vec<_>>()),
+            prop::collection::vec(0xD800u16..0xE000, 0..3),
+        ], 0..6).prop_map(|runs| runs.concat())
+    }
+
+    proptest! {
+        #[test] fn encodes_like_std(s in text()) {
+            let expected: Vec<u16> = s.encode_utf16().collect();
+            prop_assert_eq!(utf16_len(&s), expected.len());
+            for kernel in kernels() {
+                prop_assert_eq!(&encode(kernel, &s), &expected);
+            }
+            prop_assert!(eq_utf16(&expected, &s));
+            let mut other = expected.clone();
+            if let Some(last) = other.last_mut() {
+                *last ^= 1;
+                prop_assert!(!eq_utf16(&other, &s));
+            }
+            other.push(u16::from(b'a'));
+            prop_assert!(!eq_utf16(&other, &s));
+        }
+        #[test] fn decodes_like_std(units in units()) {
+            let expected = String::from_utf16(&units).ok();
+            let expected_lossy = String::from_utf16_lossy(&units);
+            for kernel in kernels() {
+                prop_assert_eq!(&decode(kernel, &units, false), &expected);
+                prop_assert_eq!(decode(kernel, &units, true).unwrap(), expected_lossy.clone());
+            }
+            prop_assert_eq!(utf8_len_lossy(&units), expected_lossy.len());
+            //long enough to split into pieces, at different places
+            let long = [&units[..], &units[..units.len() / 2]].concat().repeat(12);
+            let mut pieces = String::new();
+            decode_utf16_lossy_pieces(&long, |piece| { pieces.push_str(piece); Ok::<_, ()>(()) }).unwrap();
+            prop_assert_eq!(pieces, String::from_utf16_lossy(&long));
+        }
+        #[test] fn round_trips(s in text()) {
+            let mut units = vec![0; 1];
+            push_utf16(&mut units, &s);
+            prop_assert_eq!(try_decode_utf16(&units[1..]).unwrap(), s.clone());
+            prop_assert_eq!(decode_utf16_lossy(&units[1..]), s);
+        }
+    }
+
+    #[test] fn exact_lengths() {
+        let s = "ascii, é, 日本語, and 😀";
+        let units: Vec<u16> = s.encode_utf16().collect();
+        assert_eq!(utf8_len(&units), s.len());
+        assert_eq!(utf8_len_lossy(&[0xD800, 0xDC00, 0xDC00, 0xD800]), 10);
+        let mut exact = vec![0; utf16_len(s)];
+        assert_eq!(encode_utf16_into(s, &mut exact), exact.len());
+        assert_eq!(exact, units);
+    }
+    #[test] #[should_panic] fn too_small() {
+        encode_utf16_into("hello", &mut [0; 4]);
+    }
+}
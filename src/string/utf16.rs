@@ -0,0 +1,30 @@
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::hash::Hasher;
+use super::transcode;
+
+///Code points of UTF-16 data.  Unpaired surrogates are their own code point, so this is lossless.
+fn code_points<I: Iterator<Item=u16>>(units: I) -> impl Iterator<Item=u32> {
+    char::decode_utf16(units).map(|c| match c {
+        Ok(c) => c as u32,
+        Err(e) => e.unpaired_surrogate() as u32,
+    })
+}
+
+///Compares UTF-16 data by code point, which sorts valid strings the same as `str`.
+///
+/// This differs from comparing code units when a string contains characters outside the basic multilingual plane.
+pub(crate) fn cmp<I: Iterator<Item=u16>>(a: I, b: I) -> Ordering {
+    code_points(a).cmp(code_points(b))
+}
+
+///Hashes UTF-16 data the same way as the equivalent `str`, replacing unpaired surrogates with U+FFFD.
+pub(crate) fn hash<H: Hasher>(units: &[u16], state: &mut H) {
+    //`str` hashes as its bytes followed by 0xff.  Hashers stream what they're written, so the bytes can be written a
+    //stack buffer at a time
+    transcode::decode_utf16_lossy_pieces(units, |piece| {
+        state.write(piece.as_bytes());
+        Ok::<_, Infallible>(())
+    }).unwrap();
+    state.write_u8(0xff);
+}
@@ -1,13 +1,35 @@
-use std::mem::MaybeUninit;
 use std::hash::{Hash, Hasher};
-use std::fmt::Formatter;
-use std::ffi::{c_void, OsString};
+use std::cmp::Ordering;
+use std::fmt::{Formatter, Write};
+use std::borrow::{Borrow, Cow};
 use std::ops::Deref;
-use std::os::windows::ffi::OsStrExt;
-use windows::core::{HSTRING,InParam};
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::mem::MaybeUninit;
 use crate::release_pool::ReleasePool;
+use crate::error::Error;
+use crate::release_pool::arena::ArenaSlice;
+use crate::release_pool::stack::record_conversion;
+#[cfg(target_os = "windows")]
+use super::{ArrayString, Atom, MaybeOwnedString, SharedString};
+use super::utf16;
+use super::transcode;
+#[cfg(target_os = "windows")]
+use std::ffi::c_void;
+#[cfg(target_os = "windows")]
+use windows::core::{HSTRING,InParam};
+#[cfg(target_os = "windows")]
 use windows::Win32::System::WinRT::{HSTRING_HEADER, WindowsCreateStringReference};
+#[cfg(target_os = "windows")]
 use windows::core::PCWSTR;
+#[cfg(target_os = "windows")]
+use crate::release_pool::stack::defer_drop;
+
+///The code unit of this backend's native encoding.
+#[cfg(target_os = "windows")]
+pub(crate) type NativeUnit = u16;
 
 /**
 For reasons we will never know, Microsoft decided to cripple string interop performance
@@ -29,13 +51,15 @@ for reasons unknown.
 Basically, I am not very confident they intend to support my usecase, and to avoid coupling very tightly with them I am shipping my own compatible
 implementation, until such time as I become convinced we can play nice together.
 */
+#[cfg(target_os = "windows")]
 #[repr(C)]
-pub struct ICantBelieveItsNotHString<'a>(&'a c_void,Option<Box<[u16]>>);
+pub struct ICantBelieveItsNotHString<'a>(&'a c_void,Option<Storage>);
+#[cfg(target_os = "windows")]
 impl<'a> ICantBelieveItsNotHString<'a> {
     ///# Safety
     /// Can only pass a fast-pass hstring (e.g. created with `WindowsCreateStringReference`).
     /// Lifetime is not checked
-    unsafe fn from_fastpass_hstring(hstring: HSTRING,backing_data:Option<Box<[u16]>>) -> Self {
+    unsafe fn from_fastpass_hstring(hstring: HSTRING,backing_data:Option<Storage>) -> Self {
         //read the inner field, this should be a pointer to the HSTRING header
         //HSTRING is defined #[repr(transparent)] so we should be able to transmute it to its field
         let field: *const c_void = std::mem::transmute(hstring);
@@ -58,6 +82,7 @@ impl<'a> ICantBelieveItsNotHString<'a> {
         }
     }
 }
+#[cfg(target_os = "windows")]
 ///In previous releases of windows-rs, windows API took parameters of type `Into<Param<'a, HSTRING>>`.
 ///
 /// A couple of things seem to have happened here:
@@ -83,6 +108,7 @@ on them here.
     }
 }
 
+#[cfg(target_os = "windows")]
 /**
 In modern times, instead of taking InParam<'a, HSTRING>, windows-rs methods tend to take &HSTRING directly.
 
@@ -98,6 +124,7 @@ impl<'a> Deref for ICantBelieveItsNotHString<'a> {
 
 
 
+#[cfg(target_os = "windows")]
 impl std::fmt::Debug for ICantBelieveItsNotHString<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{:?}",self.as_hstring()))
@@ -129,9 +156,46 @@ impl<'a> StringBuilder<'a> {
 
 On Windows, this type contains a slice of 0-terminated UTF-16, followed by owned storage (if needed, for example, for static strings).
 To implement borrowed types, storage can be set to `None`.
+
+Owned storage is usually a heap-allocated vector, but conversions performed under a [ReleasePool] with an arena
+(see [ReleasePool::with_arena]) store their UTF-16 in the arena instead.  Conversions of short strings
+(up to 23 units) store their UTF-16 inline, in the storage itself, and leave the slice empty.
  */
 #[derive(Debug)]
-pub struct ParameterString<'a>(&'a [u16],Option<Box<[u16]>>);
+pub struct ParameterString<'a>(&'a [u16],Option<Storage>);
+
+///Owned storage backing a [ParameterString].
+#[derive(Debug)]
+enum Storage {
+    //keeps any spare capacity, rather than reallocating to shrink it
+    Heap(Vec<u16>),
+    //only held to keep the arena chunk alive
+    Arena(#[allow(dead_code)] ArenaSlice<u16>),
+    #[cfg(target_os = "windows")]
+    Shared(Arc<OwnedString>),
+    Inline(Inline),
+}
+
+///The number of units, including the terminator, that a [ParameterString] can hold without allocating.
+const INLINE_UNITS: usize = 24;
+
+///Null-terminated UTF-16 stored inside a [ParameterString].
+///
+/// Aligned so moving a [ParameterString] copies words rather than units.
+#[derive(Debug)]
+#[repr(align(8))]
+struct Inline {
+    //not counting the terminator
+    len: usize,
+    //initialized through `len`, inclusive
+    units: [MaybeUninit<u16>; INLINE_UNITS],
+}
+impl Inline {
+    ///The units, including the null terminator.
+    fn units_with_nul(&self) -> &[u16] {
+        unsafe{std::slice::from_raw_parts(self.units.as_ptr() as *const u16, self.len + 1)}
+    }
+}
 impl<'a> IntoParameterString<'a> for ParameterString<'a> {
     fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
         self
@@ -141,22 +205,148 @@ impl<'a> IntoParameterString<'a> for ParameterString<'a> {
 //more or less, ParameterString gets its trait implementations from the `.0` field
 impl<'a> PartialEq for ParameterString<'a> {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.units_with_nul() == other.units_with_nul()
     }
 }
 impl<'a> Eq for ParameterString<'a> {}
 
+///Hashes the same as the equivalent [str].
 impl<'a> Hash for ParameterString<'a> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.hash(state)
+        utf16::hash(self.units(), state)
     }
 }
 
 impl<'a> ParameterString<'a> {
-    ///A view into the parameter string that is utf-16, null-terminated
-    pub fn u16z_view(&self) -> U16ZKnownLength {
-        U16ZKnownLength(self.0)
+    ///Encodes units and a null terminator into the pool's arena, if it has one and the arena has room.
+    ///
+    /// `fill` writes at most `max_len` units, and returns how many it wrote.
+    fn encode_into_arena<F: FnOnce(&mut [MaybeUninit<u16>]) -> usize>(max_len: usize, fill: F, pool: &ReleasePool) -> Option<Self> {
+        let arena_slice = pool.arena()?.alloc_with(max_len + 1, |buffer| {
+            let len = fill(uninit(&mut buffer[..max_len]));
+            buffer[len] = 0;
+            len + 1
+        })?;
+        pool.record_deferred(1);
+        pool.record_deferred_bytes(std::mem::size_of_val(arena_slice.as_slice()));
+        //fool rust into letting us take &temp
+        let slice = unsafe{std::slice::from_raw_parts(arena_slice.as_slice().as_ptr(), arena_slice.as_slice().len())};
+        Some(ParameterString(slice, Some(Storage::Arena(arena_slice))))
+    }
+    ///Encodes `units` and a null terminator, like [Self::try_encode_with].
+    ///
+    /// `max_len` is an upper bound on the number of units produced by `units`, which must not include a null.
+    fn try_encode<I: Iterator<Item=u16> + Clone>(units: I, max_len: usize, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        ParameterString::try_encode_with(max_len, |buffer| fill(buffer, units.clone()), pool)
+    }
+    ///Encodes units and a null terminator inline if `max_len` units fit, otherwise into the pool's arena if it has one
+    /// and the arena has room, otherwise onto the heap.
+    ///
+    /// `fill` writes at most `max_len` units, none of them null, and returns how many it wrote.
+    fn try_encode_with<F: Fn(&mut [MaybeUninit<u16>]) -> usize>(max_len: usize, fill: F, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        if max_len < INLINE_UNITS {
+            let mut units = [MaybeUninit::uninit(); INLINE_UNITS];
+            let len = fill(&mut units[..max_len]);
+            units[len].write(0);
+            return Ok(ParameterString(&[], Some(Storage::Inline(Inline { len, units }))));
+        }
+        if let Some(parameter_string) = ParameterString::encode_into_arena(max_len, &fill, pool) {
+            return Ok(parameter_string);
+        }
+        let mut v = Vec::new();
+        v.try_reserve_exact(max_len + 1).map_err(|_| Error::out_of_memory())?;
+        let len = fill(&mut v.spare_capacity_mut()[..max_len]);
+        unsafe{v.set_len(len)};
+        v.push(0);
+        Ok(ParameterString::from_vec_with_nul(v))
+    }
+    ///Takes ownership of `v`, which must be null-terminated.
+    fn from_vec_with_nul(v: Vec<u16>) -> ParameterString<'static> {
+        //fool rust into letting us take &temp
+        let slice_ptr = v.as_ptr();
+        let slice_len = v.len();
+        ParameterString(unsafe{std::slice::from_raw_parts(slice_ptr, slice_len)}, Some(Storage::Heap(v)))
+    }
+    ///The units, including the null terminator.
+    fn units_with_nul(&self) -> &[u16] {
+        match &self.1 {
+            Some(Storage::Inline(inline)) => inline.units_with_nul(),
+            _ => self.0,
+        }
+    }
+    fn units(&self) -> &[u16] {
+        self.units_with_nul().split_last().unwrap().1
+    }
+    ///Moves inline units onto the heap, so pointers to them survive moving the storage.
+    #[cfg(target_os = "windows")]
+    fn into_stable(self) -> Self {
+        if let Some(Storage::Inline(inline)) = &self.1 {
+            return ParameterString::from_vec_with_nul(inline.units_with_nul().to_vec());
+        }
+        self
+    }
+}
+
+///Writes `units` to the start of `buffer`, which must have room for them, and returns how many were written.
+fn fill<I: Iterator<Item=u16>>(buffer: &mut [MaybeUninit<u16>], units: I) -> usize {
+    let mut len = 0;
+    for (slot, unit) in buffer.iter_mut().zip(units) {
+        slot.write(unit);
+        len += 1;
+    }
+    len
+}
+///Views initialized units as a buffer to overwrite.
+fn uninit(units: &mut [u16]) -> &mut [MaybeUninit<u16>] {
+    //only initialized values are written through the result
+    unsafe{&mut *(units as *mut [u16] as *mut [MaybeUninit<u16>])}
+}
+
+//read-back implementations shared by the string types.  `units` excludes the null terminator.
+fn chars(units: &[u16]) -> impl Iterator<Item=char> + '_ {
+    char::decode_utf16(units.iter().copied()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+fn to_string_lossy(units: &[u16]) -> Cow<'_, str> {
+    Cow::Owned(transcode::decode_utf16_lossy(units))
+}
+fn try_to_string(units: &[u16]) -> Result<String, Error> {
+    transcode::try_decode_utf16(units)
+}
+fn len_utf8(units: &[u16]) -> usize {
+    transcode::utf8_len_lossy(units)
+}
+fn len_utf16(units: &[u16]) -> usize {
+    //an unpaired surrogate and U+FFFD are both one unit
+    units.len()
+}
+#[cfg(target_os = "windows")]
+fn to_os_string(units: &[u16]) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+    //only strings that aren't unicode need the slower path
+    transcode::try_decode_utf16(units).map(OsString::from).unwrap_or_else(|_| OsString::from_wide(units))
+}
+///Other platforms can't represent unpaired surrogates in an [OsString], so they're replaced with U+FFFD.
+#[cfg(not(target_os = "windows"))]
+fn to_os_string(units: &[u16]) -> OsString {
+    OsString::from(transcode::decode_utf16_lossy(units))
+}
+///Compares with a Rust string, without allocating.
+fn eq_str(units: &[u16], other: &str) -> bool {
+    transcode::eq_utf16(units, other)
+}
+///Writes the string, replacing invalid data with U+FFFD.
+fn fmt_lossy(units: &[u16], f: &mut Formatter<'_>) -> std::fmt::Result {
+    transcode::decode_utf16_lossy_pieces(units, |piece| f.write_str(piece))
+}
+///Writes the string, escaping unpaired surrogates as `\u{d800}`.
+fn fmt_escaped(units: &[u16], f: &mut Formatter<'_>) -> std::fmt::Result {
+    for c in char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => f.write_char(c)?,
+            Err(e) => write!(f, "\\u{{{:x}}}", e.unpaired_surrogate())?,
+        }
     }
+    Ok(())
 }
 
 ///Type that can be converted into a platform string parameter.
@@ -185,6 +375,7 @@ impl<'a> ParameterString<'a> {
 /// 5.  A standard library type, like [String].
 ///
 pub trait IntoParameterString<'a> {
+    #[cfg(target_os = "windows")]
     ///Converts into an hstring 'trampoline'.  For reasons why this is not an hstring directly,
     /// see [ICantBelieveItsNotHString].
     /// * `header`: A pointer to `HSTRING_HEADER`.  In some cases, this will be used in the conversion.
@@ -204,94 +395,591 @@ pub trait IntoParameterString<'a> {
     /// let mut header = MaybeUninit::uninit();
     /// let h = unsafe{e.into_hstring_trampoline(&mut header)};
     /// ```
+    ///
+    /// # Panics
+    /// If [try_into_hstring_trampoline](Self::try_into_hstring_trampoline) would fail.
     unsafe fn into_hstring_trampoline<'h,'r: 'a + 'h>(self, header: &'h mut MaybeUninit<HSTRING_HEADER>) -> ICantBelieveItsNotHString<'r> where Self: Sized  + 'a {
+        self.try_into_hstring_trampoline(header).unwrap()
+    }
+    #[cfg(target_os = "windows")]
+    ///Converts into an hstring 'trampoline', or fails if the string can't be converted.
+    ///
+    /// See [into_hstring_trampoline](Self::into_hstring_trampoline) and [try_into_parameter_string](Self::try_into_parameter_string).
+    ///
+    /// # Safety
+    /// See [into_hstring_trampoline](Self::into_hstring_trampoline).
+    unsafe fn try_into_hstring_trampoline<'h,'r: 'a + 'h>(self, header: &'h mut MaybeUninit<HSTRING_HEADER>) -> Result<ICantBelieveItsNotHString<'r>, Error> where Self: Sized  + 'a {
         //not needed on windows
         let pool = ReleasePool::assuming_pool();
-        let parameter_string = self.into_parameter_string(pool);
+        //the storage moves into the trampoline, so it can't hold the units inline
+        let parameter_string = self.try_into_parameter_string(pool)?.into_stable();
         let mut hstring = MaybeUninit::uninit();
         //ok to transmute here because windows won't mutate our string\
         //and because parameter_string is null-terminated
         let pwstr = PCWSTR(std::mem::transmute(parameter_string.0.as_ptr()));
-        WindowsCreateStringReference(pwstr, parameter_string.0.len() as u32 - 1, header.assume_init_mut(), Some(hstring.assume_init_mut())).unwrap();
-        ICantBelieveItsNotHString::from_fastpass_hstring(hstring.assume_init(),parameter_string.1)
+        WindowsCreateStringReference(pwstr, parameter_string.0.len() as u32 - 1, header.assume_init_mut(), Some(hstring.assume_init_mut()))?;
+        Ok(ICantBelieveItsNotHString::from_fastpass_hstring(hstring.assume_init(),parameter_string.1))
     }
+    #[cfg(target_os = "windows")]
     ///Converts into a null-terminated PWCSTR.
     ///
+    /// If the string has to be converted, the converted storage is deferred into the innermost pool on this thread.
+    ///
     /// # Safety
     /// * The resulting pwcstr will be valid only
-    /// * For the lifetime of the `self` parameter
+    /// * For the lifetime of the `self` parameter, and until the innermost pool on this thread is drained or dropped
     /// * When the underlying PCWSTR is not modified.  e.g., you must pass it to a function of type LPCWSTR
     ///
     /// Note that the type returned here may be different than the PCWSTR in use in some other library.  Therefore,
     /// you may need to transmute "this" type into "that" type.
+    ///
+    /// # Panics
+    /// If the string has to be converted, and no pool is active on this thread.
     unsafe fn into_unsafe_const_pwzstr(self) -> PCWSTR where Self: Sized {
         //not needed on windows
         let pool = ReleasePool::assuming_pool();
-        let parameter_string = self.into_parameter_string(pool);
-        PCWSTR(std::mem::transmute(parameter_string.0.as_ptr()))
+        //the storage outlives this function, so it can't hold the units inline
+        let parameter_string = self.into_parameter_string(pool).into_stable();
+        let pwstr = PCWSTR(parameter_string.0.as_ptr());
+        if let Some(storage) = parameter_string.1 {
+            //the heap, arena or shared storage doesn't move, so the pointer stays valid
+            defer_drop(storage);
+        }
+        pwstr
     }
 
     ///Converts into an erased type
     ///
     /// For compatibility with macOS, this takes a releasepool parameter
+    ///
+    /// # Panics
+    /// If [try_into_parameter_string](Self::try_into_parameter_string) would fail.
     fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a>;
+
+    ///Converts into an erased type, or fails if the string can't be converted.
+    ///
+    /// Conversions fail if the string contains an interior null (which would truncate it), can't be encoded natively,
+    /// or storage for the converted string can't be allocated.
+    ///
+    /// Types whose conversion can't fail use this default implementation, which calls [into_parameter_string](Self::into_parameter_string).
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> where Self: Sized {
+        Ok(self.into_parameter_string(pool))
+    }
 }
 
+///Encodes a [str].  Strings that fit inline are widened as ASCII in the same pass that checks them; longer strings are
+/// measured first so storage is allocated at their exact length.
+fn try_encode_str(s: &str, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+    //a str never has fewer bytes than UTF-16 units, so a short one fits inline whatever it contains
+    if s.len() < INLINE_UNITS {
+        let mut inline = Inline { len: s.len(), units: [MaybeUninit::uninit(); INLINE_UNITS] };
+        let mut ascii = true;
+        let mut nul = false;
+        for (slot, &byte) in inline.units.iter_mut().zip(s.as_bytes()) {
+            ascii &= byte < 0x80;
+            nul |= byte == 0;
+            slot.write(u16::from(byte));
+        }
+        if nul {
+            return Err(Error::interior_nul());
+        }
+        if !ascii {
+            //start over, now that it's known to need transcoding
+            inline.len = transcode::encode_utf16(s, &mut inline.units[..s.len()]);
+        }
+        inline.units[inline.len].write(0);
+        return Ok(ParameterString(&[], Some(Storage::Inline(inline))));
+    }
+    if s.as_bytes().contains(&0) {
+        return Err(Error::interior_nul());
+    }
+    ParameterString::try_encode_with(transcode::utf16_len(s), |buffer| transcode::encode_utf16(s, buffer), pool)
+}
+///Collects formatted text as UTF-16.
+struct Utf16Writer(Vec<u16>);
+impl Write for Utf16Writer {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        transcode::push_utf16(&mut self.0, s);
+        Ok(())
+    }
+}
+///Formats straight into UTF-16.  A format string without arguments is encoded without the formatting machinery.
+impl<'a> IntoParameterString<'a> for std::fmt::Arguments<'a> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        match self.as_str() {
+            Some(piece) => try_encode_str(piece, pool),
+            None => try_format_utf16(self).map(ParameterString::from_vec_with_nul),
+        }
+    }
+}
+///Formats `args` into null-terminated UTF-16, or fails if the result contains a null.
+fn try_format_utf16(args: std::fmt::Arguments<'_>) -> Result<Vec<u16>, Error> {
+    let mut writer = Utf16Writer(Vec::new());
+    match args.as_str() {
+        //a format string without arguments skips the formatting machinery
+        Some(piece) => {
+            writer.0.try_reserve_exact(piece.len() + 1).map_err(|_| Error::out_of_memory())?;
+            transcode::push_utf16(&mut writer.0, piece);
+        }
+        None => writer.write_fmt(args).expect("a formatting trait implementation returned an error"),
+    }
+    let mut units = writer.0;
+    if units.contains(&0) {
+        return Err(Error::interior_nul());
+    }
+    units.try_reserve_exact(1).map_err(|_| Error::out_of_memory())?;
+    units.push(0);
+    Ok(units)
+}
 ///Implements conversions, primarily by copying
 impl<'a> IntoParameterString<'a> for &'a str {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        try_encode_str(self, pool)
+    }
+}
+/**
+A borrowed platform string, like [str] or [OsStr] for the platform's encoding.
+
+[OwnedString], [PStr] and [ParameterString] all dereference to it, so functions that only read a string can take
+a `&NativeStr` without consuming or converting anything.
+
+```
+use pcore::string::{NativeStr, OwnedString};
+use pcore::release_pool::autoreleasepool;
+fn shout(string: &NativeStr) -> String {
+    string.to_string_lossy().to_uppercase()
+}
+autoreleasepool(|pool| {
+    assert_eq!(shout(&OwnedString::new("hi", pool)), "HI");
+    assert_eq!(shout(&pcore::pstr!("hi")), "HI");
+})
+```
+*/
+#[repr(transparent)]
+pub struct NativeStr(
+    //null-terminated
+    [u16]
+);
+impl NativeStr {
+    ///Views `units`, which must be null-terminated.
+    fn from_units_with_nul(units: &[u16]) -> &NativeStr {
+        //NativeStr is a transparent wrapper around [u16]
+        unsafe{&*(units as *const [u16] as *const NativeStr)}
+    }
+    fn units(&self) -> &[u16] {
+        self.0.split_last().unwrap().1
+    }
+    ///A view into the string that is utf-16, null-terminated
+    pub fn u16z_view(&self) -> U16ZKnownLength<'_> {
+        U16ZKnownLength(&self.0)
+    }
+    ///Converts to a Rust string, replacing invalid data with U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        to_string_lossy(self.units())
+    }
+    ///Converts to a Rust string, or fails if the string contains invalid data.
+    pub fn try_to_string(&self) -> Result<String, Error> {
+        try_to_string(self.units())
+    }
+    ///The length of the string in UTF-8 code units (bytes).  Invalid data counts as U+FFFD.
+    pub fn len_utf8(&self) -> usize {
+        len_utf8(self.units())
+    }
+    ///The length of the string in UTF-16 code units.  Invalid data counts as U+FFFD.
+    pub fn len_utf16(&self) -> usize {
+        len_utf16(self.units())
+    }
+    ///Whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.units().is_empty()
+    }
+    ///Iterates over the characters of the string, replacing invalid data with U+FFFD.
+    pub fn chars(&self) -> impl Iterator<Item=char> + '_ {
+        chars(self.units())
+    }
+    ///Converts to an [OsString].  On Windows this is lossless, even for unpaired surrogates.
+    pub fn to_os_string(&self) -> OsString {
+        to_os_string(self.units())
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a NativeStr {
     fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
-        //convert to utf16z
-        let encode = self.encode_utf16();
-        //reserve capacity for size_hint + 1 for null
-        let size_hint = encode.size_hint();
-        let mut v = Vec::with_capacity(size_hint.1.unwrap_or(size_hint.0) + 1);
-        for item in encode {
-            v.push(item);
+        ParameterString(&self.0, None)
+    }
+}
+impl ToOwned for NativeStr {
+    type Owned = OwnedString;
+    fn to_owned(&self) -> OwnedString {
+        OwnedString(self.0.to_vec())
+    }
+}
+impl AsRef<NativeStr> for NativeStr {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl std::fmt::Display for NativeStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.units(), f)
+    }
+}
+impl std::fmt::Debug for NativeStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.units(), f)
+    }
+}
+impl PartialEq for NativeStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+impl Eq for NativeStr {}
+///Hashes the same as the equivalent [str].
+impl Hash for NativeStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        utf16::hash(self.units(), state)
+    }
+}
+impl PartialOrd for NativeStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for NativeStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        utf16::cmp(self.units().iter().copied(), other.units().iter().copied())
+    }
+}
+impl PartialEq<str> for NativeStr {
+    fn eq(&self, other: &str) -> bool {
+        eq_str(self.units(), other)
+    }
+}
+impl PartialEq<&str> for NativeStr {
+    fn eq(&self, other: &&str) -> bool {
+        eq_str(self.units(), other)
+    }
+}
+impl<'a> Deref for ParameterString<'a> {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_units_with_nul(self.units_with_nul())
+    }
+}
+impl<'a> AsRef<NativeStr> for ParameterString<'a> {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Deref for PStr {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_units_with_nul(self.0)
+    }
+}
+impl AsRef<NativeStr> for PStr {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Deref for OwnedString {
+    type Target = NativeStr;
+    fn deref(&self) -> &NativeStr {
+        NativeStr::from_units_with_nul(&self.0)
+    }
+}
+impl AsRef<NativeStr> for OwnedString {
+    fn as_ref(&self) -> &NativeStr {
+        self
+    }
+}
+impl Borrow<NativeStr> for OwnedString {
+    fn borrow(&self) -> &NativeStr {
+        self
+    }
+}
+
+///Borrows the string's inline storage, without copying or converting it.
+#[cfg(target_os = "windows")]
+impl<'a, const N: usize> IntoParameterString<'a> for &'a ArrayString<N> {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(self.units_with_nul(), None)
+    }
+}
+///Borrows the interned string, without copying or converting it.
+#[cfg(target_os = "windows")]
+impl IntoParameterString<'static> for Atom {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.0.into_parameter_string(pool)
+    }
+}
+///Hands over the string's storage, without copying or converting it.
+impl IntoParameterString<'static> for OwnedString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'static> {
+        ParameterString::from_vec_with_nul(self.0)
+    }
+}
+///Borrows a borrowed string, and hands over the storage of an owned one.
+#[cfg(target_os = "windows")]
+impl<'a> IntoParameterString<'a> for MaybeOwnedString<'a> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        match self {
+            MaybeOwnedString::Borrowed(string) => string.into_parameter_string(pool),
+            MaybeOwnedString::Owned(string) => string.into_parameter_string(pool),
         }
-        v.push(0);
-        let boxed_slice = v.into_boxed_slice();
+    }
+}
+#[cfg(target_os = "windows")]
+impl<'a, 'b> IntoParameterString<'b> for &'b MaybeOwnedString<'a> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'b> {
+        (**self).into_parameter_string(pool)
+    }
+}
+///Borrows the text of a parameter string that borrows it, and otherwise takes over its storage where possible.
+#[cfg(target_os = "windows")]
+impl<'a> From<ParameterString<'a>> for MaybeOwnedString<'a> {
+    fn from(string: ParameterString<'a>) -> Self {
+        match string {
+            ParameterString(units, None) => MaybeOwnedString::Borrowed(NativeStr::from_units_with_nul(units)),
+            string => MaybeOwnedString::Owned(OwnedString::take_parameter_string(string)),
+        }
+    }
+}
+///Shares the string's storage, without copying or converting it.
+#[cfg(target_os = "windows")]
+impl IntoParameterString<'static> for SharedString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'static> {
+        let shared = self.0;
         //fool rust into letting us take &temp
-        let slice_ptr = boxed_slice.as_ptr();
-        let slice_len = boxed_slice.len();
-        ParameterString(unsafe{std::slice::from_raw_parts(slice_ptr, slice_len)}, Some(boxed_slice))
+        let slice = unsafe{std::slice::from_raw_parts(shared.0.as_ptr(), shared.0.len())};
+        ParameterString(slice, Some(Storage::Shared(shared)))
+    }
+}
+///Borrows the string, without copying or converting it.
+#[cfg(target_os = "windows")]
+impl<'a> IntoParameterString<'a> for &'a SharedString {
+    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
+        ParameterString(&self.0.0, None)
     }
 }
+
 ///An instance created by the [pstr!] macro.  This is a static string.
 ///
 /// Instances can be created with the [pstr!] macro.
-#[derive(Copy,Clone,Debug)]
+#[derive(Copy,Clone,PartialEq,Eq)]
 pub struct PStr(pub &'static [u16]);
+impl PStr {
+    fn units(&self) -> &'static [u16] {
+        self.0.split_last().unwrap().1
+    }
+    #[cfg(target_os = "windows")]
+    pub(crate) fn native_str(self) -> &'static NativeStr {
+        NativeStr::from_units_with_nul(self.0)
+    }
+}
 impl IntoParameterString<'static> for PStr {
     fn into_parameter_string(self,_pool: &ReleasePool) -> ParameterString<'static> {
         ParameterString(self.0, None)
     }
 }
 
-impl ToString for PStr {
-    fn to_string(&self) -> String {
-        unsafe{widestring::U16CStr::from_slice_with_nul_unchecked(self.0)}.to_string().unwrap()
+impl std::fmt::Display for PStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.units(), f)
+    }
+}
+impl std::fmt::Debug for PStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_escaped(self.units(), f)
+    }
+}
+///Hashes the same as the equivalent [str].
+impl Hash for PStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        utf16::hash(self.units(), state)
+    }
+}
+impl PartialOrd for PStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for PStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        utf16::cmp(self.units().iter().copied(), other.units().iter().copied())
+    }
+}
+///The empty string.
+impl Default for PStr {
+    fn default() -> Self {
+        PStr(&[0])
+    }
+}
+impl PartialEq<str> for PStr {
+    fn eq(&self, other: &str) -> bool {
+        eq_str(self.units(), other)
+    }
+}
+impl PartialEq<&str> for PStr {
+    fn eq(&self, other: &&str) -> bool {
+        eq_str(self.units(), other)
     }
 }
 
-impl<'a> IntoParameterString<'static> for OsString {
-    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'static> {
-        let owned_storage: Vec<u16> = self.encode_wide().chain(std::iter::once(0)).collect();
-        let boxed_bytes = owned_storage.into_boxed_slice();
-        //fool rust into letting us take &temp
-        let slice_ptr = boxed_bytes.as_ptr();
-        let slice_len = boxed_bytes.len();
-        ParameterString(unsafe{std::slice::from_raw_parts(slice_ptr, slice_len)}, Some(boxed_bytes))
+#[cfg(target_os = "windows")]
+fn try_encode_wide(s: &OsStr) -> Result<impl Iterator<Item=u16> + Clone + '_, Error> {
+    use std::os::windows::ffi::OsStrExt;
+    Ok(s.encode_wide())
+}
+///Other platforms have no UTF-16 form of [OsStr], so data that isn't unicode can't be encoded.
+#[cfg(not(target_os = "windows"))]
+fn try_encode_wide(_s: &OsStr) -> Result<std::iter::Empty<u16>, Error> {
+    Err(Error::invalid_encoding())
+}
+///Encodes an [OsStr], which never has fewer bytes than utf16 code units.
+fn try_encode_os_str(s: &OsStr, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+    //unicode goes through the transcoder
+    if let Some(s) = s.to_str() {
+        return try_encode_str(s, pool);
+    }
+    if s.as_encoded_bytes().contains(&0) {
+        return Err(Error::interior_nul());
     }
+    ParameterString::try_encode(try_encode_wide(s)?, s.len(), pool)
 }
 
-impl<'a> IntoParameterString<'a> for &'a std::path::Path {
-    fn into_parameter_string(self, _pool: &ReleasePool) -> ParameterString<'a> {
-        let encoded = widestring::U16CString::from_os_str(self.as_os_str()).unwrap();
-        let boxed = encoded.into_vec_with_nul().into_boxed_slice();
-        //fool rust into letting us take &temp
-        let slice_ptr = boxed.as_ptr();
-        let slice_len = boxed.len();
-        ParameterString(unsafe{std::slice::from_raw_parts(slice_ptr, slice_len)}, Some(boxed))
+impl IntoParameterString<'static> for OsString {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        try_encode_os_str(&self, pool)
+    }
+}
+
+impl<'a> IntoParameterString<'a> for &'a OsStr {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        try_encode_os_str(self, pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a Path {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.as_os_str().into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        self.as_os_str().try_into_parameter_string(pool)
+    }
+}
+impl IntoParameterString<'static> for PathBuf {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.into_os_string().into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        self.into_os_string().try_into_parameter_string(pool)
+    }
+}
+
+//Rust strings are utf8, so these all require encoding into new storage
+impl IntoParameterString<'static> for String {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        try_encode_str(&self, pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for &'a String {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.as_str().into_parameter_string(pool)
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        self.as_str().try_into_parameter_string(pool)
+    }
+}
+impl IntoParameterString<'static> for Box<str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        try_encode_str(&self, pool)
+    }
+}
+impl IntoParameterString<'static> for Rc<str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        try_encode_str(&self, pool)
+    }
+}
+impl IntoParameterString<'static> for Arc<str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        try_encode_str(&self, pool)
+    }
+}
+impl<'a> IntoParameterString<'a> for Cow<'a, str> {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        try_encode_str(&self, pool)
+    }
+}
+///C strings are treated as UTF-8.
+impl<'a> IntoParameterString<'a> for &'a CStr {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'a> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'a>, Error> {
+        record_conversion();
+        try_encode_str(self.to_str().map_err(|_| Error::invalid_encoding())?, pool)
+    }
+}
+///C strings are treated as UTF-8.
+impl IntoParameterString<'static> for CString {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        try_encode_str(self.to_str().map_err(|_| Error::invalid_encoding())?, pool)
+    }
+}
+impl IntoParameterString<'static> for char {
+    fn into_parameter_string(self, pool: &ReleasePool) -> ParameterString<'static> {
+        self.try_into_parameter_string(pool).unwrap()
+    }
+    fn try_into_parameter_string(self, pool: &ReleasePool) -> Result<ParameterString<'static>, Error> {
+        record_conversion();
+        if self == '\0' {
+            return Err(Error::interior_nul());
+        }
+        let mut buffer = [0; 2];
+        let units = self.encode_utf16(&mut buffer);
+        ParameterString::try_encode(units.iter().copied(), units.len(), pool)
     }
 }
 
@@ -300,12 +988,15 @@ pub struct U16ZKnownLength<'a>(&'a [u16]);
 impl<'a> U16ZKnownLength<'a> {
     ///Converts to an owned type, by cloning.  This erases the lifetime of the type
     pub fn to_owned(&self) -> OwnedString {
-        OwnedString(self.0.clone().to_vec().into_boxed_slice())
+        OwnedString(self.0.to_vec())
     }
     ///Returns a utf16 null-terminated slice
     pub fn utf16z_slice(&self) -> &[u16] {
         self.0
     }
+    fn units(&self) -> &[u16] {
+        self.0.split_last().unwrap().1
+    }
 }
 impl<'a> IntoParameterString<'a> for U16ZKnownLength<'a> {
     fn into_parameter_string(self,_pool: &ReleasePool) -> ParameterString<'a> {
@@ -314,46 +1005,85 @@ impl<'a> IntoParameterString<'a> for U16ZKnownLength<'a> {
 }
 
 ///Represents a null-terminated string of length not known at runtime
-pub struct U16ZErasedLength<'a>(&'a [u16]);
+///
+/// Use [U16ZErasedLength::with_u16_unknown_length] for buffers that might not be terminated, such as ones from untrusted sources.
+/// Finding the length of (or converting) such a buffer panics if it turns out to have no terminator; [U16ZErasedLength::find_length_within]
+/// never does.
+pub struct U16ZErasedLength<'a> {
+    units: &'a [u16],
+    //whether the caller promised a terminator, which the pointer fast path relies on
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    terminated: bool,
+}
 impl<'a> U16ZErasedLength<'a> {
     ///# Safety
     /// * The slice must have a null-terminator
     pub unsafe fn with_u16_z_unknown_length(slice: &'a [u16]) -> Self {
-        Self(slice)
+        Self { units: slice, terminated: true }
     }
-    fn len_with_z(&self) -> usize {
-        //scan the string for \0
-        for i in 0..self.0.len() {
-            if self.0[i] == 0 {
-                return i+1;
-            }
-        }
-        panic!("U16Z is not null-terminated")
+    ///Wraps a slice that may or may not contain a null-terminator.
+    pub fn with_u16_unknown_length(slice: &'a [u16]) -> Self {
+        Self { units: slice, terminated: false }
     }
-    pub fn find_length(&self) -> U16ZKnownLength {
-        let actual_len = self.len_with_z();
-        let adjusted_slice = unsafe{std::slice::from_raw_parts(self.0.as_ptr(), actual_len)};
-        U16ZKnownLength(&adjusted_slice)
+    ///Finds the terminator within the first `max` units of the slice, or returns `None` if there isn't one.
+    ///
+    /// This doesn't rely on the slice being null-terminated, so it's safe to use on buffers from untrusted sources.
+    pub fn find_length_within(&self, max: usize) -> Option<U16ZKnownLength<'a>> {
+        let units = &self.units[..max.min(self.units.len())];
+        find_nul(units).map(|nul| U16ZKnownLength(&units[..=nul]))
+    }
+    ///Finds the terminator.
+    ///
+    /// # Panics
+    /// If the slice isn't null-terminated.
+    pub fn find_length(&self) -> U16ZKnownLength<'a> {
+        self.find_length_within(self.units.len()).expect("U16Z is not null-terminated")
     }
 }
 impl<'a> std::fmt::Debug for U16ZErasedLength<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let actual_len = self.len_with_z() - 1;
-        let adjusted_slice = unsafe{std::slice::from_raw_parts(self.0.as_ptr(), actual_len)};
-        let s = String::from_utf16(adjusted_slice).unwrap();
-        f.write_str(&s)
+        match self.find_length_within(self.units.len()) {
+            Some(known) => fmt_escaped(known.units(), f),
+            None => fmt_escaped(self.units, f),
+        }
     }
 }
 impl<'a> IntoParameterString<'a> for &U16ZErasedLength<'a> {
     fn into_parameter_string(self,_pool: &ReleasePool) -> ParameterString<'a> {
-        let actual_len = self.len_with_z();
-        let actual_slice = unsafe{std::slice::from_raw_parts(self.0.as_ptr(), actual_len)};
-        ParameterString(actual_slice, None)
+        ParameterString(self.find_length().0, None)
     }
+    #[cfg(target_os = "windows")]
     unsafe fn into_unsafe_const_pwzstr(self) -> PCWSTR where Self: Sized {
-        //faster path without finding out length
-        PCWSTR(std::mem::transmute(self.0.as_ptr()))
+        if self.terminated {
+            //faster path without finding out length
+            PCWSTR(std::mem::transmute(self.units.as_ptr()))
+        }
+        else {
+            PCWSTR(std::mem::transmute(self.find_length().0.as_ptr()))
+        }
+    }
+}
+
+///Finds the first null in `units`, comparing a word of units at a time.
+fn find_nul(units: &[u16]) -> Option<usize> {
+    const LANES: usize = std::mem::size_of::<usize>() / 2;
+    //0x0001 in every lane
+    const LOW: usize = usize::MAX / 0xFFFF;
+    const HIGH: usize = LOW << 15;
+    //any bit pattern is a valid usize
+    let (head, words, _) = unsafe{units.align_to::<usize>()};
+    if let Some(nul) = head.iter().position(|&u| u == 0) {
+        return Some(nul);
+    }
+    let mut start = head.len();
+    for &word in words {
+        //sets the high bit of the lowest lane that's zero, if any
+        if word.wrapping_sub(LOW) & !word & HIGH != 0 {
+            break;
+        }
+        start += LANES;
     }
+    units[start..].iter().position(|&u| u == 0).map(|nul| start + nul)
 }
 
 /**
@@ -376,27 +1106,53 @@ impl MyType {
 }
 ```
  */
-pub struct OwnedString(Box<[u16]>);
+#[derive(Clone,PartialEq,Eq)]
+pub struct OwnedString(Vec<u16>);
 impl OwnedString {
     pub fn new<'a, S: IntoParameterString<'a>>(string: S, pool: &ReleasePool) -> Self {
-        let parameter_string = string.into_parameter_string(pool);
-        let boxed = match parameter_string {
-            ParameterString(_, Some(b)) => {
-                //move the box into the new type
-                b
+        OwnedString::take_parameter_string(string.into_parameter_string(pool))
+    }
+    ///Takes over the storage of `parameter_string` where possible, otherwise copies it.
+    fn take_parameter_string(parameter_string: ParameterString<'_>) -> Self {
+        let units = match parameter_string {
+            ParameterString(_, Some(Storage::Heap(v))) => {
+                //move the vector into the new type
+                v
+            }
+            #[cfg(target_os = "windows")]
+            ParameterString(_, Some(Storage::Shared(shared))) => {
+                //if this was the last reference, take the vector out of it
+                Arc::try_unwrap(shared).map(|owned| owned.0).unwrap_or_else(|shared| shared.0.clone())
             }
-            ParameterString(slice,None) => {
+            parameter_string => {
                 //will require a clone
-                slice.to_owned().into_boxed_slice()
+                parameter_string.units_with_nul().to_owned()
             }
         };
-        Self(boxed)
+        Self(units)
+    }
+    ///Takes ownership of `v`, which must be null-terminated.  Its spare capacity is kept rather than reallocated away.
+    pub(crate) fn from_vec_with_nul(v: Vec<u16>) -> Self {
+        OwnedString(v)
+    }
+    ///Formats `args` into a new string.  This is what [pformat!](crate::pformat) expands to.
+    ///
+    /// Panics if [Self::try_format] would fail.
+    pub fn format(args: std::fmt::Arguments<'_>) -> Self {
+        OwnedString::try_format(args).unwrap()
+    }
+    ///Formats `args` into a new string, or fails if the result contains a null.
+    pub fn try_format(args: std::fmt::Arguments<'_>) -> Result<Self, Error> {
+        record_conversion();
+        try_format_utf16(args).map(OwnedString::from_vec_with_nul)
+    }
+    fn units(&self) -> &[u16] {
+        self.0.split_last().unwrap().1
     }
 }
-impl ToString for OwnedString {
-    fn to_string(&self) -> String {
-        let s = &self.0.split_last().unwrap().1;
-        String::from_utf16(s).unwrap()
+impl std::fmt::Display for OwnedString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_lossy(self.units(), f)
     }
 }
 
@@ -407,9 +1163,203 @@ impl<'a> IntoParameterString<'a> for &'a OwnedString {
 }
 impl std::fmt::Debug for OwnedString {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let s = &self.0.split_last().unwrap().1;
-        let str = String::from_utf16(s).unwrap();
-        f.write_str(&str)
+        fmt_escaped(self.units(), f)
+    }
+}
+///Hashes the same as the equivalent [str].
+impl Hash for OwnedString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        utf16::hash(self.units(), state)
+    }
+}
+impl PartialOrd for OwnedString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+///Sorts the same as the equivalent [str].
+impl Ord for OwnedString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        utf16::cmp(self.units().iter().copied(), other.units().iter().copied())
+    }
+}
+///The empty string.
+impl Default for OwnedString {
+    fn default() -> Self {
+        OwnedString(vec![0])
+    }
+}
+impl PartialEq<str> for OwnedString {
+    fn eq(&self, other: &str) -> bool {
+        eq_str(self.units(), other)
+    }
+}
+impl PartialEq<&str> for OwnedString {
+    fn eq(&self, other: &&str) -> bool {
+        eq_str(self.units(), other)
+    }
+}
+
+///Assembles an [OwnedString] a piece at a time, in the platform's encoding.
+///
+/// Rust text is encoded as it's appended, and platform strings are appended as-is.
+///
+/// ```
+/// use pcore::string::OwnedStringBuilder;
+/// use std::fmt::Write;
+/// let mut builder = OwnedStringBuilder::new();
+/// builder.push_str("hello");
+/// builder.push(' ');
+/// write!(builder, "{}", 42).unwrap();
+/// assert_eq!(builder.finish(), "hello 42");
+/// ```
+#[derive(Default)]
+pub struct OwnedStringBuilder {
+    //UTF-16, without a null terminator
+    units: Vec<u16>,
+}
+impl OwnedStringBuilder {
+    ///Creates an empty builder.
+    pub fn new() -> Self {
+        OwnedStringBuilder::default()
+    }
+    ///Creates an empty builder with room for `capacity` code units in the platform's encoding.
+    pub fn with_capacity(capacity: usize) -> Self {
+        OwnedStringBuilder { units: Vec::with_capacity(capacity) }
+    }
+    ///Reserves room for at least `additional` more code units in the platform's encoding.
+    pub fn reserve(&mut self, additional: usize) {
+        self.units.reserve(additional)
+    }
+    ///Appends a Rust string.
+    pub fn push_str(&mut self, s: &str) {
+        transcode::push_utf16(&mut self.units, s);
+    }
+    ///Appends a character.
+    pub fn push(&mut self, c: char) {
+        self.units.extend_from_slice(c.encode_utf16(&mut [0; 2]));
+    }
+    ///Appends a platform string, without re-encoding it.
+    pub fn push_native(&mut self, string: &ParameterString<'_>) {
+        self.units.extend_from_slice(string.units());
+    }
+    ///Finishes the string.
+    ///
+    /// Panics if [Self::try_finish] would fail.
+    pub fn finish(self) -> OwnedString {
+        self.try_finish().unwrap()
+    }
+    ///Finishes the string, or fails if a null was appended.
+    pub fn try_finish(self) -> Result<OwnedString, Error> {
+        let mut units = self.units;
+        if units.contains(&0) {
+            return Err(Error::interior_nul());
+        }
+        units.try_reserve_exact(1).map_err(|_| Error::out_of_memory())?;
+        units.push(0);
+        Ok(OwnedString::from_vec_with_nul(units))
+    }
+}
+impl Write for OwnedStringBuilder {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+    fn write_char(&mut self, c: char) -> std::fmt::Result {
+        self.push(c);
+        Ok(())
+    }
+}
+impl Extend<char> for OwnedStringBuilder {
+    fn extend<I: IntoIterator<Item=char>>(&mut self, iter: I) {
+        for c in iter {
+            self.push(c);
+        }
+    }
+}
+impl<'a> Extend<&'a str> for OwnedStringBuilder {
+    fn extend<I: IntoIterator<Item=&'a str>>(&mut self, iter: I) {
+        for s in iter {
+            self.push_str(s);
+        }
+    }
+}
+
+/**
+Type that can be created from a platform string.  This is the reverse of [IntoParameterString].
+
+Binding authors can return any conforming type, and let the caller choose:
+
+```
+use pcore::string::{FromPlatformString, IntoParameterString};
+use pcore::release_pool::ReleasePool;
+use pcore::error::Error;
+fn name<T: FromPlatformString>(pool: &ReleasePool) -> Result<T, Error> {
+    //in a real binding, this would be the result of an OS API
+    let parameter_string = "name".into_parameter_string(pool);
+    T::from_parameter_string(parameter_string, pool)
+}
+let pool = unsafe{ReleasePool::new()};
+let name: String = name(&pool).unwrap();
+assert_eq!(name, "name");
+```
+
+Conversions fail if the string can't be represented in the output type, such as an unpaired surrogate in a [String].
+
+Implementing it only requires converting from a borrowed [NativeStr], which is the same on every platform:
+
+```
+use pcore::string::{FromPlatformString, NativeStr};
+use pcore::error::Error;
+struct Shouted(String);
+impl FromPlatformString for Shouted {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        view.try_to_string().map(|s| Shouted(s.to_uppercase()))
+    }
+}
+assert_eq!(Shouted::from_native_str(&pcore::pstr!("hi")).unwrap().0, "HI");
+```
+*/
+pub trait FromPlatformString: Sized {
+    ///Converts from a parameter string, reusing its storage where possible.
+    fn from_parameter_string(string: ParameterString<'_>, _pool: &ReleasePool) -> Result<Self, Error> {
+        Self::from_native_str(&string)
+    }
+    ///Converts from a borrowed platform string.
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error>;
+}
+impl FromPlatformString for String {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        view.try_to_string()
+    }
+}
+impl FromPlatformString for Box<str> {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        String::from_native_str(view).map(String::into_boxed_str)
+    }
+}
+///On Windows, this is lossless.  Other platforms can't represent unpaired surrogates in an [OsString].
+impl FromPlatformString for OsString {
+    #[cfg(target_os = "windows")]
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        Ok(view.to_os_string())
+    }
+    #[cfg(not(target_os = "windows"))]
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        String::from_native_str(view).map(OsString::from)
+    }
+}
+impl FromPlatformString for PathBuf {
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        OsString::from_native_str(view).map(PathBuf::from)
+    }
+}
+impl FromPlatformString for OwnedString {
+    fn from_parameter_string(string: ParameterString<'_>, pool: &ReleasePool) -> Result<Self, Error> {
+        Ok(OwnedString::new(string, pool))
+    }
+    fn from_native_str(view: &NativeStr) -> Result<Self, Error> {
+        Ok(view.to_owned())
     }
 }
 
@@ -426,16 +1376,38 @@ pub use wchar::wchz as __wchz;
 /// let e = pstr!("test");
 ///
 /// ```
+#[cfg(target_os = "windows")]
 #[macro_export]
 macro_rules! pstr {
     ($expr:literal) => {
         {
-            pcore::string::PStr(pcore::string::__wchz!($expr))
+            pcore::string::PStr(pcore::string::__wchz!(u16, $expr))
         }
 
     }
 }
+/// Like [pstr!](crate::pstr), but for [utf16_backend](crate::string::utf16_backend).
+///
+/// This macro is defined to return a [PStr]
+/// ```
+/// use pcore::utf16_pstr;
+/// let e = utf16_pstr!("test");
+///
+/// ```
+#[cfg(not(target_os = "windows"))]
+#[macro_export]
+macro_rules! utf16_pstr {
+    ($expr:literal) => {
+        {
+            pcore::string::utf16_backend::PStr(pcore::string::utf16_backend::__wchz!(u16, $expr))
+        }
 
+    }
+}
+#[cfg(all(test, not(target_os = "windows")))]
+use crate::utf16_pstr as pstr;
+
+#[cfg(target_os = "windows")]
 #[test] fn str_call() {
     use windows::Foundation::Uri;
     let f = "https://sealedabstract.com";
@@ -445,6 +1417,7 @@ macro_rules! pstr {
     Uri::CreateUri(&hstr).unwrap();
 }
 
+#[cfg(target_os = "windows")]
 #[test] fn static_into() {
     use windows::Foundation::Uri;
     let f = pstr!("https://sealedabstract.com");
@@ -454,6 +1427,7 @@ macro_rules! pstr {
     //call some API that requires IntoParam
     Uri::CreateUri(&hstr).unwrap();
 }
+#[cfg(target_os = "windows")]
 #[test] fn os_string() {
     use windows::Foundation::Uri;
     use std::str::FromStr;
@@ -464,6 +1438,7 @@ macro_rules! pstr {
     Uri::CreateUri(&hstr).unwrap();
 }
 
+#[cfg(target_os = "windows")]
 #[test] fn path() {
     use std::path::PathBuf;
     use std::str::FromStr;
@@ -478,4 +1453,286 @@ macro_rules! pstr {
 #[test] fn to_string() {
     let p = pstr!("Hello world");
     assert_eq!(p.to_string(), "Hello world");
-}
\ No newline at end of file
+}
+#[test] fn arena_conversion() {
+    use crate::release_pool::autoreleasepool_with_arena;
+    autoreleasepool_with_arena(1024, |pool| {
+        let parameter_string = "hello, this string is too long to store inline".into_parameter_string(pool);
+        assert!(matches!(parameter_string.1, Some(Storage::Arena(_))));
+        assert_eq!(parameter_string.u16z_view().utf16z_slice()[..6], [104, 101, 108, 108, 111, 44]);
+        assert_eq!(OwnedString::new(parameter_string, pool).to_string(), "hello, this string is too long to store inline");
+        //exceeding the budget falls back to the heap
+        let long = "x".repeat(1024);
+        let parameter_string = long.as_str().into_parameter_string(pool);
+        assert!(matches!(parameter_string.1, Some(Storage::Heap(_))));
+    })
+}
+
+#[test] fn owned_string_reuses_storage() {
+    let pool = unsafe{ReleasePool::new()};
+    let parameter_string = "hello, this string is too long to store inline".into_parameter_string(&pool);
+    let ptr = parameter_string.0.as_ptr();
+    let owned = OwnedString::new(parameter_string, &pool);
+    assert_eq!(owned.0.as_ptr(), ptr);
+    assert_eq!(owned.to_string(), "hello, this string is too long to store inline");
+}
+
+#[test] fn inline_conversion() {
+    use crate::release_pool::autoreleasepool_with_arena;
+    autoreleasepool_with_arena(1024, |pool| {
+        let short = "héllo 😀".into_parameter_string(pool);
+        assert!(matches!(short.1, Some(Storage::Inline(_))));
+        assert_eq!(pool.arena_used(), Some(0));
+        //the units move with the storage
+        let moved = Box::new(short);
+        assert_eq!(&**moved, "héllo 😀");
+        assert_eq!(moved.u16z_view().utf16z_slice().last(), Some(&0));
+        assert_eq!(OwnedString::new(*moved, pool), "héllo 😀");
+        //the terminator needs the last inline unit
+        let longest = "x".repeat(INLINE_UNITS - 1);
+        assert!(matches!(longest.as_str().into_parameter_string(pool).1, Some(Storage::Inline(_))));
+        let too_long = "x".repeat(INLINE_UNITS);
+        assert!(matches!(too_long.as_str().into_parameter_string(pool).1, Some(Storage::Arena(_))));
+    })
+}
+
+#[test] fn transcoding() {
+    let pool = unsafe{ReleasePool::new()};
+    let samples = ["", "a", "hello world, in ASCII", "héllo wörld", "😀😀", "日本語のテキスト", "ascii then 日本語 then ascii again, long enough for words", "\u{7f}\u{80}\u{7ff}\u{800}\u{ffff}\u{10000}\u{10ffff}"];
+    for sample in samples {
+        //cross the inline limit
+        for prefix in [0, 1, 8, INLINE_UNITS - 2, INLINE_UNITS, 200] {
+            let s = "p".repeat(prefix) + sample;
+            let expected: Vec<u16> = s.encode_utf16().collect();
+            assert_eq!(transcode::utf16_len(&s), expected.len());
+            let parameter_string = s.as_str().into_parameter_string(&pool);
+            assert_eq!(parameter_string.units(), expected);
+            assert_eq!(parameter_string.units_with_nul().len(), expected.len() + 1);
+        }
+    }
+}
+
+#[test] fn erased_length() {
+    let storage = [104, 105, 0, 106];
+    let erased = unsafe{U16ZErasedLength::with_u16_z_unknown_length(&storage)};
+    assert_eq!(erased.find_length().utf16z_slice(), &[104, 105, 0]);
+    assert_eq!(format!("{:?}", erased), "hi");
+    assert_eq!(erased.find_length_within(3).unwrap().utf16z_slice(), &[104, 105, 0]);
+    assert!(erased.find_length_within(2).is_none());
+    assert!(erased.find_length_within(100).is_some());
+
+    //no terminator at all, at every alignment and position a word scan might get wrong
+    let unterminated: [u16; 40] = std::array::from_fn(|i| [1, 0x8000, 0xFFFF, 0x100][i % 4]);
+    assert_eq!(format!("{:?}", U16ZErasedLength::with_u16_unknown_length(&[104, 105])), "hi");
+    for start in 0..8 {
+        let erased = U16ZErasedLength::with_u16_unknown_length(&unterminated[start..]);
+        assert!(erased.find_length_within(usize::MAX).is_none());
+        for nul in start..40 {
+            let mut storage = unterminated;
+            storage[nul] = 0;
+            let erased = U16ZErasedLength::with_u16_unknown_length(&storage[start..]);
+            assert_eq!(erased.find_length().utf16z_slice().len(), nul - start + 1);
+        }
+    }
+}
+
+#[test] fn read_back() {
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new("héllo 😀", &pool);
+    assert_eq!(owned.try_to_string().unwrap(), "héllo 😀");
+    assert_eq!(owned.len_utf8(), "héllo 😀".len());
+    assert_eq!(owned.len_utf16(), 8);
+    assert!(!owned.is_empty());
+    assert_eq!(owned.chars().count(), 7);
+
+    let storage = [104, 0xD800, 105, 0];
+    let unpaired = U16ZKnownLength(&storage).into_parameter_string(&pool);
+    assert!(unpaired.try_to_string().is_err());
+    assert_eq!(unpaired.to_string_lossy(), "h\u{FFFD}i");
+    assert_eq!(unpaired.len_utf8(), 5);
+    assert_eq!(unpaired.len_utf16(), 3);
+    assert!("".into_parameter_string(&pool).is_empty());
+}
+
+#[test] fn unpaired_surrogate() {
+    let storage = [104, 0xD800, 105, 0];
+    let owned = U16ZKnownLength(&storage).to_owned();
+    assert_eq!(owned.to_string(), "h\u{FFFD}i");
+    assert_eq!(format!("{:?}", owned), "h\\u{d800}i");
+    let erased = unsafe{U16ZErasedLength::with_u16_z_unknown_length(&storage)};
+    assert_eq!(format!("{:?}", erased), "h\\u{d800}i");
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        let units: Vec<u16> = owned.to_os_string().encode_wide().collect();
+        assert_eq!(units, [104, 0xD800, 105]);
+    }
+    #[cfg(not(target_os = "windows"))]
+    assert_eq!(owned.to_os_string(), "h\u{FFFD}i");
+}
+
+#[test] fn interior_nul() {
+    let pool = unsafe{ReleasePool::new()};
+    assert!("a\0b".try_into_parameter_string(&pool).is_err());
+    assert!(OsString::from("a\0b").try_into_parameter_string(&pool).is_err());
+    assert!(std::path::Path::new("a\0b").try_into_parameter_string(&pool).is_err());
+    assert_eq!("ab".try_into_parameter_string(&pool).unwrap().u16z_view().utf16z_slice(), &[97, 98, 0]);
+    assert!(pstr!("ab").try_into_parameter_string(&pool).is_ok());
+}
+
+#[test] fn traits() {
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new("hello", &pool);
+    assert_eq!(owned.clone(), owned);
+    assert_eq!(owned, "hello");
+    assert_eq!(owned, *"hello");
+    assert_ne!(owned, "hell");
+    assert!(OwnedString::new("a", &pool) < OwnedString::new("ab", &pool));
+    assert!(OwnedString::default().is_empty());
+    assert_eq!(OwnedString::default(), "");
+    assert_eq!(pstr!("hello"), "hello");
+    assert_eq!(format!("{:?}", pstr!("hello")), "hello");
+    assert_eq!(PStr::default(), "");
+}
+
+#[test] fn consistent_with_str() {
+    use std::collections::hash_map::DefaultHasher;
+    fn hash<T: Hash + ?Sized>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+    let pool = unsafe{ReleasePool::new()};
+    //U+FF61 sorts after U+1F600 by UTF-16 code unit, but before it by scalar value
+    let strs = ["", "a", "ab", "b", "héllo", "\u{FF61}", "😀", &"x".repeat(300), &"héllo 😀".repeat(60), &("x".to_owned() + &"😀".repeat(200))];
+    for a in strs {
+        let owned = OwnedString::new(a, &pool);
+        assert_eq!(hash(&owned), hash(a));
+        assert_eq!(hash(&a.into_parameter_string(&pool)), hash(a));
+        for b in strs {
+            assert_eq!(owned.cmp(&OwnedString::new(b, &pool)), a.cmp(b), "{:?} {:?}", a, b);
+        }
+    }
+    assert_eq!(hash(&pstr!("héllo")), hash("héllo"));
+    assert!(pstr!("\u{FF61}") < pstr!("😀"));
+}
+
+#[test] fn std_types() {
+    let pool = unsafe{ReleasePool::new()};
+    fn check<'a, S: IntoParameterString<'a>>(s: S, pool: &ReleasePool) {
+        assert_eq!(s.into_parameter_string(pool).u16z_view().utf16z_slice(), &[104, 105, 0]);
+    }
+    let string = String::from("hi");
+    check(&string, &pool);
+    check(string, &pool);
+    check(Box::<str>::from("hi"), &pool);
+    check(Rc::<str>::from("hi"), &pool);
+    check(Arc::<str>::from("hi"), &pool);
+    check(Cow::Borrowed("hi"), &pool);
+    check(Cow::<str>::Owned("hi".to_owned()), &pool);
+    check(OsStr::new("hi"), &pool);
+    check(OsString::from("hi"), &pool);
+    check(PathBuf::from("hi"), &pool);
+    check(Path::new("hi"), &pool);
+    check(CString::new("hi").unwrap(), &pool);
+    check(CString::new("hi").unwrap().as_c_str(), &pool);
+    assert_eq!('😀'.into_parameter_string(&pool).u16z_view().utf16z_slice(), &[0xD83D, 0xDE00, 0]);
+    assert!('\0'.try_into_parameter_string(&pool).is_err());
+    assert!(CString::new(vec![0xFF]).unwrap().try_into_parameter_string(&pool).is_err());
+}
+
+#[test] fn from_platform_string() {
+    let pool = unsafe{ReleasePool::new()};
+    fn check<T: FromPlatformString>(pool: &ReleasePool) -> T {
+        T::from_parameter_string("hi".into_parameter_string(pool), pool).unwrap()
+    }
+    assert_eq!(check::<String>(&pool), "hi");
+    assert_eq!(&*check::<Box<str>>(&pool), "hi");
+    assert_eq!(check::<OsString>(&pool), "hi");
+    assert_eq!(check::<PathBuf>(&pool), Path::new("hi"));
+    assert_eq!(check::<OwnedString>(&pool), "hi");
+    let storage = [104, 0xD800, 0];
+    let unpaired = U16ZKnownLength(&storage).into_parameter_string(&pool);
+    assert!(String::from_native_str(&unpaired).is_err());
+    assert_eq!(OwnedString::from_native_str(&unpaired).unwrap().len_utf16(), 2);
+}
+
+#[test] fn pformat() {
+    let pool = unsafe{ReleasePool::new()};
+    let name = "wörld";
+    //what pformat! expands to, but for this backend
+    assert_eq!(OwnedString::format(format_args!("hello {}", name)), "hello wörld");
+    assert_eq!(OwnedString::format(format_args!("{{static}}")), "{static}");
+    assert_eq!(format_args!("{}-{}", 1, 2).into_parameter_string(&pool).to_string_lossy(), "1-2");
+    assert_eq!(format_args!("piece").into_parameter_string(&pool).to_string_lossy(), "piece");
+    assert!(OwnedString::try_format(format_args!("a{}b", '\0')).is_err());
+    assert!(OwnedString::try_format(format_args!("a\0b")).is_err());
+    assert!(format_args!("a{}b", '\0').try_into_parameter_string(&pool).is_err());
+}
+
+#[test] fn pformat_records_conversion() {
+    crate::release_pool::autoreleasepool(|pool| {
+        let before = pool.innermost_statistics().conversions;
+        let _ = OwnedString::format(format_args!("hello {}", 1));
+        assert_eq!(pool.innermost_statistics().conversions, before + 1);
+        let _ = format_args!("piece").into_parameter_string(pool);
+        assert_eq!(pool.innermost_statistics().conversions, before + 2);
+    })
+}
+
+#[test] fn builder() {
+    let pool = unsafe{ReleasePool::new()};
+    let mut builder = OwnedStringBuilder::with_capacity(4);
+    builder.push_str("héllo");
+    builder.push(' ');
+    builder.push('😀');
+    builder.reserve(16);
+    builder.push_native(&pstr!(" wörld").into_parameter_string(&pool));
+    builder.extend(['!', '?']);
+    builder.extend([" a", "b"]);
+    write!(builder, " {}", 42).unwrap();
+    let owned = builder.finish();
+    assert_eq!(owned, "héllo 😀 wörld!? ab 42");
+    assert_eq!(OwnedStringBuilder::new().finish(), "");
+    let mut builder = OwnedStringBuilder::new();
+    builder.push('\0');
+    assert!(builder.try_finish().is_err());
+}
+
+#[test] fn native_str() {
+    use std::collections::HashMap;
+    let pool = unsafe{ReleasePool::new()};
+    let owned = OwnedString::new("héllo", &pool);
+    let native: &NativeStr = &owned;
+    assert_eq!(native, "héllo");
+    assert_eq!(native.len_utf8(), 6);
+    assert_eq!(native.to_string(), "héllo");
+    assert_eq!(&*"héllo".into_parameter_string(&pool), native);
+    assert_eq!(&*pstr!("hi"), "hi");
+    assert_eq!(native.to_owned(), owned);
+    assert_eq!(OwnedString::new(native, &pool), owned);
+    let mut map = HashMap::new();
+    map.insert(owned.clone(), 1);
+    assert_eq!(map.get(native), Some(&1));
+    fn len<S: AsRef<NativeStr>>(string: S) -> usize {
+        string.as_ref().len_utf16()
+    }
+    assert_eq!(len(&owned), 5);
+    assert_eq!(len(pstr!("hi")), 2);
+    assert_eq!(native.u16z_view().utf16z_slice(), &[104, 233, 108, 108, 111, 0]);
+}
+
+#[cfg(target_os = "windows")]
+#[test] fn unsafe_const_pwzstr() {
+    let pool = unsafe{ReleasePool::new()};
+    let inline = unsafe{"hi".into_unsafe_const_pwzstr()};
+    let boxed = unsafe{"hello, this string is too long to store inline".into_unsafe_const_pwzstr()};
+    let borrowed = unsafe{pstr!("hi").into_unsafe_const_pwzstr()};
+    //would likely reuse the conversions' allocations, had they been freed
+    let reuse = [vec![0u16; 3], vec![0u16; 48]];
+    assert_eq!(unsafe{inline.as_wide()}, &*"hi".encode_utf16().collect::<Vec<_>>());
+    assert_eq!(unsafe{boxed.as_wide()}, &*"hello, this string is too long to store inline".encode_utf16().collect::<Vec<_>>());
+    assert_eq!(unsafe{borrowed.as_wide()}, &*"hi".encode_utf16().collect::<Vec<_>>());
+    assert_eq!(pool.innermost_deferred(), 2);
+    drop(reuse);
+}